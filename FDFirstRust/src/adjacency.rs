@@ -0,0 +1,85 @@
+/// Bits per word of a row.
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Square boolean matrix packed into 64-bit words, one row of `⌈N / 64⌉` words per agent.
+/// Used for the tie sets of the network layers: a row is the neighborhood of an agent, so degrees
+/// and common neighbors are popcounts over a handful of words rather than scans over N cells.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdjacencyMatrix {
+    n: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl AdjacencyMatrix {
+    /// An `n` × `n` matrix without ties.
+    pub fn new(n: usize) -> Self {
+        let words_per_row = n.div_ceil(WORD_BITS);
+        AdjacencyMatrix { n, words_per_row, words: vec![0; n * words_per_row] }
+    }
+
+    /// Number of rows (and columns).
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// The packed words of row `i`; the bits past column N - 1 are always zero.
+    pub fn row(&self, i: usize) -> &[u64] {
+        &self.words[i * self.words_per_row..(i + 1) * self.words_per_row]
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> bool {
+        self.words[i * self.words_per_row + j / WORD_BITS] >> (j % WORD_BITS) & 1 == 1
+    }
+
+    /// Sets the single cell (i, j); see `set_tie` for an undirected tie.
+    pub fn set(&mut self, i: usize, j: usize, value: bool) {
+        let word = &mut self.words[i * self.words_per_row + j / WORD_BITS];
+        let mask = 1 << (j % WORD_BITS);
+        if value {
+            *word |= mask;
+        } else {
+            *word &= !mask;
+        }
+    }
+
+    /// Sets both (i, j) and (j, i).
+    pub fn set_tie(&mut self, i: usize, j: usize, value: bool) {
+        self.set(i, j, value);
+        self.set(j, i, value);
+    }
+
+    /// Number of set cells in row `i`, i.e. the degree of agent i.
+    pub fn degree(&self, i: usize) -> usize {
+        self.row(i).iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Number of columns set in both row `i` and row `j`, i.e. the common neighbors of agents i and j.
+    pub fn common_neighbors(&self, i: usize, j: usize) -> usize {
+        self.row(i).iter().zip(self.row(j)).map(|(a, b)| (a & b).count_ones() as usize).sum()
+    }
+
+    /// The columns set in row `i`, in increasing order.
+    pub fn neighbors(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        self.row(i).iter().enumerate().flat_map(|(w, &word)| {
+            let mut rest = word;
+            std::iter::from_fn(move || {
+                if rest == 0 {
+                    return None;
+                }
+                let bit = rest.trailing_zeros() as usize;
+                rest &= rest - 1;
+                Some(w * WORD_BITS + bit)
+            })
+        })
+    }
+
+    /// The neighbors of every agent, in increasing order.
+    pub fn to_adj_list(&self) -> Vec<Vec<usize>> {
+        (0..self.n).map(|i| self.neighbors(i).collect()).collect()
+    }
+}
@@ -0,0 +1,230 @@
+/// Bits per word.
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Fixed-length vector of bits packed into 64-bit words; the bits past the end are always zero.
+/// Holds the beliefs of an agent and the reality, dimension `bundle * M_IN_BUNDLE + element`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitVector {
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl BitVector {
+    pub fn new(len: usize) -> Self {
+        BitVector { len, words: vec![0; len.div_ceil(WORD_BITS)] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        self.words[i / WORD_BITS] >> (i % WORD_BITS) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        let mask = 1 << (i % WORD_BITS);
+        if value {
+            self.words[i / WORD_BITS] |= mask;
+        } else {
+            self.words[i / WORD_BITS] &= !mask;
+        }
+    }
+
+    pub fn flip(&mut self, i: usize) {
+        self.words[i / WORD_BITS] ^= 1 << (i % WORD_BITS);
+    }
+
+    /// Number of bits in which `self` and `other` differ, i.e. their Hamming distance.
+    pub fn num_differing(&self, other: &BitVector) -> usize {
+        self.words.iter().zip(&other.words).map(|(a, b)| (a ^ b).count_ones() as usize).sum()
+    }
+
+    /// The indices of the set bits, in increasing order.
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(w, &word)| {
+            let mut rest = word;
+            std::iter::from_fn(move || {
+                if rest == 0 {
+                    return None;
+                }
+                let bit = rest.trailing_zeros() as usize;
+                rest &= rest - 1;
+                Some(w * WORD_BITS + bit)
+            })
+        })
+    }
+
+    /// A vector of `len` bits from its words, clearing the bits past the end.
+    pub fn from_words(len: usize, mut words: Vec<u64>) -> Self {
+        if let Some(last) = words.last_mut().filter(|_| !len.is_multiple_of(WORD_BITS)) {
+            *last &= (1 << (len % WORD_BITS)) - 1;
+        }
+        BitVector { len, words }
+    }
+}
+
+/// The bits of each bundle, as (word, mask) pairs: a bundle is right when no bit under its masks differs from reality.
+pub struct BundleMasks {
+    masks: Vec<Vec<(usize, u64)>>,
+}
+
+impl BundleMasks {
+    pub fn new(m_of_bundle: usize, m_in_bundle: usize) -> Self {
+        let masks = (0..m_of_bundle)
+            .map(|bundle| {
+                let mut masks: Vec<(usize, u64)> = Vec::new();
+                for i in bundle * m_in_bundle..(bundle + 1) * m_in_bundle {
+                    let (word, bit) = (i / WORD_BITS, 1 << (i % WORD_BITS));
+                    match masks.last_mut() {
+                        Some((last, mask)) if *last == word => *mask |= bit,
+                        _ => masks.push((word, bit)),
+                    }
+                }
+                masks
+            })
+            .collect();
+        BundleMasks { masks }
+    }
+
+    /// Number of bundles in which `first` and `second` agree on every bit.
+    pub fn num_agreeing(&self, first: &BitVector, second: &BitVector) -> usize {
+        let (first, second) = (first.words(), second.words());
+        self.masks
+            .iter()
+            .filter(|masks| masks.iter().all(|&(word, mask)| (first[word] ^ second[word]) & mask == 0))
+            .count()
+    }
+}
+
+/// Per-bit counts of the set bits over a number of bit vectors, kept bit-sliced:
+/// plane p holds bit p of every count, so adding a vector is a ripple of word-wide half adders.
+pub struct BitCounter {
+    len: usize,
+    planes: Vec<Vec<u64>>,
+}
+
+impl BitCounter {
+    pub fn new(len: usize) -> Self {
+        BitCounter { len, planes: Vec::new() }
+    }
+
+    pub fn add(&mut self, vector: &BitVector) {
+        let mut carry = vector.words().to_vec();
+        for plane in self.planes.iter_mut() {
+            let mut is_carried = false;
+            for (word, carry) in plane.iter_mut().zip(carry.iter_mut()) {
+                let sum = *word ^ *carry;
+                *carry &= *word;
+                *word = sum;
+                is_carried |= *carry != 0;
+            }
+            if !is_carried {
+                return;
+            }
+        }
+        self.planes.push(carry);
+    }
+
+    /// The bits whose count exceeds `threshold` and those whose count equals it.
+    pub fn compare(&self, threshold: usize) -> (BitVector, BitVector) {
+        let num_word = self.len.div_ceil(WORD_BITS);
+        if threshold >> self.planes.len() != 0 {
+            // Every count is below 2^planes.
+            return (BitVector::new(self.len), BitVector::new(self.len));
+        }
+        let mut greater = vec![0; num_word];
+        let mut equal = vec![u64::MAX; num_word];
+        for (p, plane) in self.planes.iter().enumerate().rev() {
+            let threshold_bit = if threshold >> p & 1 == 1 { u64::MAX } else { 0 };
+            for ((greater, equal), &count_bit) in greater.iter_mut().zip(equal.iter_mut()).zip(plane) {
+                *greater |= *equal & count_bit & !threshold_bit;
+                *equal &= !(count_bit ^ threshold_bit);
+            }
+        }
+        (BitVector::from_words(self.len, greater), BitVector::from_words(self.len, equal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    fn random_vector(len: usize, rng: &mut ChaCha8Rng) -> BitVector {
+        let mut vector = BitVector::new(len);
+        for i in 0..len {
+            vector.set(i, rng.random());
+        }
+        vector
+    }
+
+    #[test]
+    fn bit_vectors_match_naive_bits() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for len in [1, 63, 64, 65, 130] {
+            let first = random_vector(len, &mut rng);
+            let second = random_vector(len, &mut rng);
+            let naive_ones: Vec<usize> = (0..len).filter(|&i| first.get(i)).collect();
+            assert_eq!(first.ones().collect::<Vec<usize>>(), naive_ones);
+            assert_eq!(first.num_differing(&second), (0..len).filter(|&i| first.get(i) != second.get(i)).count());
+            assert_eq!(BitVector::from_words(len, vec![u64::MAX; len.div_ceil(WORD_BITS)]).ones().count(), len);
+        }
+    }
+
+    #[test]
+    fn bundle_masks_match_naive_counts() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        // Bundles of 7 straddle the word boundaries.
+        for (m_of_bundle, m_in_bundle) in [(20, 5), (19, 7), (3, 64), (1, 1)] {
+            let len = m_of_bundle * m_in_bundle;
+            let masks = BundleMasks::new(m_of_bundle, m_in_bundle);
+            let reality = random_vector(len, &mut rng);
+            for _ in 0..50 {
+                // Mostly agreeing, so that whole bundles agree.
+                let mut belief = reality.clone();
+                for i in 0..len {
+                    if rng.random_bool(0.05) {
+                        belief.flip(i);
+                    }
+                }
+                let naive = (0..m_of_bundle)
+                    .filter(|bundle| (bundle * m_in_bundle..(bundle + 1) * m_in_bundle).all(|i| belief.get(i) == reality.get(i)))
+                    .count();
+                assert_eq!(masks.num_agreeing(&belief, &reality), naive);
+            }
+        }
+    }
+
+    #[test]
+    fn bit_counters_match_naive_counts() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        for len in [5, 64, 100] {
+            for num_vector in [0, 1, 2, 3, 7, 8, 9, 20] {
+                let vectors: Vec<BitVector> = (0..num_vector).map(|_| random_vector(len, &mut rng)).collect();
+                let mut counter = BitCounter::new(len);
+                for vector in vectors.iter() {
+                    counter.add(vector);
+                }
+                let count: Vec<usize> = (0..len).map(|i| vectors.iter().filter(|vector| vector.get(i)).count()).collect();
+                for threshold in 0..num_vector + 3 {
+                    let (greater, equal) = counter.compare(threshold);
+                    for (i, &count) in count.iter().enumerate() {
+                        assert_eq!(greater.get(i), count > threshold);
+                        assert_eq!(equal.get(i), count == threshold);
+                    }
+                    assert_eq!(greater.len(), len);
+                }
+            }
+        }
+    }
+}
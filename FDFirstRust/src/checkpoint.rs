@@ -0,0 +1,71 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::experiment_manager::OutcomeVariable;
+use crate::params::ExperimentConfig;
+
+/// Partial results of `flat run`, saved every `checkpoint_interval` iterations.
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The design (including the seed) the results were obtained under.
+    pub design: ExperimentConfig,
+    /// Iterations whose observations are already in `results`.
+    pub finished: Vec<usize>,
+    pub outcome_names: Vec<String>,
+    pub results: Vec<OutcomeVariable>,
+}
+
+impl Checkpoint {
+    /// Reads a checkpoint, or `None` if there is none at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Option<Self>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut checkpoint: Checkpoint = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        checkpoint.design.set_second_order_params();
+        Ok(Some(checkpoint))
+    }
+
+    /// Writes the checkpoint next to `path` first and then moves it over,
+    /// so that an interruption while saving leaves the previous checkpoint intact.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let partial = path.with_extension("partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        serde_json::to_writer(&mut writer, self).map_err(io::Error::other)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(&partial, path)
+    }
+
+    /// Checks that the checkpoint was saved under `config`, up to the seed and the run settings.
+    pub fn check_design(&self, config: &ExperimentConfig) -> io::Result<()> {
+        if self.design.same_design(config) {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the checkpoint was saved under a different experiment design",
+            ))
+        }
+    }
+}
+
+/// Stores float arrays by their bit patterns: JSON has no NaN, which several metrics can take,
+/// and the restored accumulators are bit-for-bit those that were saved.
+pub mod f64_bits {
+    use ndarray::ArrayD;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(array: &ArrayD<f64>, serializer: S) -> Result<S::Ok, S::Error> {
+        array.mapv(f64::to_bits).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ArrayD<f64>, D::Error> {
+        Ok(ArrayD::<u64>::deserialize(deserializer)?.mapv(f64::from_bits))
+    }
+}
@@ -0,0 +1,80 @@
+use std::io;
+use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand};
+use crate::params::{ExperimentConfig, Shard};
+
+/// Command-line interface of the simulation.
+#[derive(Parser)]
+#[command(name = "flat", version, about = "Flat design simulation")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run every iteration of the design and write the results to an *.h5 file.
+    Run {
+        #[command(flatten)]
+        design: DesignArgs,
+        /// Continue from the checkpoint of an interrupted run of the same design.
+        #[arg(long)]
+        resume: bool,
+        /// Run only the k-th of n disjoint slices of the iterations, to be combined with `merge`.
+        #[arg(long, value_name = "K/N")]
+        shard: Option<Shard>,
+    },
+    /// Run each parameter combination once and export the networks as *.csv files.
+    Sample {
+        #[command(flatten)]
+        design: DesignArgs,
+        /// Also export the networks at t = 0.
+        #[arg(long)]
+        init: bool,
+    },
+    /// Combine the result files of every shard of a design into one result file.
+    Merge {
+        /// The *.h5 files written by `run --shard`.
+        #[arg(required = true, num_args = 1..)]
+        files: Vec<PathBuf>,
+        /// The merged *.h5 file to write.
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Print the stored parameters and the dataset shapes of a result file.
+    Inspect {
+        /// The *.h5 file to inspect.
+        file: PathBuf,
+    },
+}
+
+/// Options shared by the subcommands that run the design.
+#[derive(Args)]
+pub struct DesignArgs {
+    /// Experiment design (*.toml or *.json); the default design is used when omitted.
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Number of worker threads; overrides `max_thread`.
+    #[arg(short = 'j', long)]
+    pub threads: Option<usize>,
+    /// Directory for the output files; overrides `output_dir`.
+    #[arg(short, long, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+    /// Override a design parameter, e.g. `--set n=100 --set span=[2,4]`.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+impl DesignArgs {
+    /// Loads the design file and applies the command-line overrides on top of it.
+    pub fn load_config(&self) -> io::Result<ExperimentConfig> {
+        let mut config = ExperimentConfig::load(self.config.as_deref(), &self.overrides)?;
+        if let Some(threads) = self.threads {
+            config.max_thread = threads;
+        }
+        if let Some(output_dir) = &self.output_dir {
+            config.output_dir = output_dir.clone();
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,243 @@
+use rand::seq::SliceRandom;
+use crate::scenario::ScenarioRng;
+
+/// Communities found in a network.
+pub struct Partition {
+    pub community_of: Vec<usize>,
+    pub num_community: usize,
+    pub modularity: f64,
+}
+
+/// Louvain community detection (Blondel et al., 2008) on an unweighted network:
+/// nodes are moved greedily to the neighboring community that raises modularity the most,
+/// in an order shuffled by `rng`, then every community is merged into a single node and
+/// the moves are repeated on the merged network until modularity no longer rises.
+pub fn louvain(adj_list: &[Vec<usize>], rng: &mut ScenarioRng) -> Partition {
+    let n = adj_list.len();
+    let mut community_of: Vec<usize> = (0..n).collect();
+
+    // Weighted network being merged; `loops[i]` is the weight of the ties inside node i.
+    let mut neighbors: Vec<Vec<(usize, f64)>> = adj_list
+        .iter()
+        .map(|row| row.iter().map(|&j| (j, 1.0)).collect())
+        .collect();
+    let mut loops = vec![0.0; n];
+
+    loop {
+        let (community_of_node, num_community) = move_nodes(&neighbors, &loops, rng);
+        if num_community == neighbors.len() {
+            break;
+        }
+        for community in community_of.iter_mut() {
+            *community = community_of_node[*community];
+        }
+
+        let mut merged_neighbors: Vec<Vec<(usize, f64)>> = vec![Vec::new(); num_community];
+        let mut merged_loops = vec![0.0; num_community];
+        let mut weight_to = vec![0.0; num_community];
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); num_community];
+        for (node, &community) in community_of_node.iter().enumerate() {
+            members[community].push(node);
+        }
+        for (community, nodes) in members.iter().enumerate() {
+            let mut touched = Vec::new();
+            for &node in nodes {
+                merged_loops[community] += loops[node];
+                for &(neighbor, weight) in &neighbors[node] {
+                    let other = community_of_node[neighbor];
+                    if other == community {
+                        // Seen from both ends.
+                        merged_loops[community] += weight / 2.0;
+                    } else {
+                        if weight_to[other] == 0.0 {
+                            touched.push(other);
+                        }
+                        weight_to[other] += weight;
+                    }
+                }
+            }
+            for other in touched {
+                merged_neighbors[community].push((other, weight_to[other]));
+                weight_to[other] = 0.0;
+            }
+        }
+        neighbors = merged_neighbors;
+        loops = merged_loops;
+    }
+
+    let num_community = community_of.iter().max().map_or(0, |&max| max + 1);
+    let modularity = modularity(adj_list, &community_of);
+    Partition { community_of, num_community, modularity }
+}
+
+/// One level of Louvain: local moves until no node changes community.
+/// Returns the community of each node, numbered from 0, and the number of communities.
+fn move_nodes(neighbors: &[Vec<(usize, f64)>], loops: &[f64], rng: &mut ScenarioRng) -> (Vec<usize>, usize) {
+    let n = neighbors.len();
+    let degree: Vec<f64> = (0..n)
+        .map(|i| neighbors[i].iter().map(|&(_, weight)| weight).sum::<f64>() + 2.0 * loops[i])
+        .collect();
+    let total_degree: f64 = degree.iter().sum();
+    let mut community_of: Vec<usize> = (0..n).collect();
+    if total_degree == 0.0 {
+        return (community_of, n);
+    }
+    let mut community_degree = degree.clone();
+    let mut weight_to = vec![0.0; n];
+    let mut order: Vec<usize> = (0..n).collect();
+
+    let mut is_moved = true;
+    while is_moved {
+        is_moved = false;
+        order.shuffle(rng);
+        for &node in &order {
+            let current = community_of[node];
+            let mut touched = vec![current];
+            for &(neighbor, weight) in &neighbors[node] {
+                let community = community_of[neighbor];
+                if weight_to[community] == 0.0 && community != current {
+                    touched.push(community);
+                }
+                weight_to[community] += weight;
+            }
+            community_degree[current] -= degree[node];
+
+            // Gain of joining a community, up to terms common to every community.
+            let gain = |community: usize| weight_to[community] - community_degree[community] * degree[node] / total_degree;
+            let mut best = current;
+            let mut best_gain = gain(current);
+            for &community in touched.iter().skip(1) {
+                let gain = gain(community);
+                if gain > best_gain {
+                    best = community;
+                    best_gain = gain;
+                }
+            }
+
+            community_degree[best] += degree[node];
+            community_of[node] = best;
+            if best != current {
+                is_moved = true;
+            }
+            for community in touched {
+                weight_to[community] = 0.0;
+            }
+        }
+    }
+
+    let mut index_of = vec![usize::MAX; n];
+    let mut num_community = 0;
+    for community in community_of.iter_mut() {
+        if index_of[*community] == usize::MAX {
+            index_of[*community] = num_community;
+            num_community += 1;
+        }
+        *community = index_of[*community];
+    }
+    (community_of, num_community)
+}
+
+/// Newman's modularity Q of a partition of an unweighted network; 0 for a network without ties.
+pub fn modularity(adj_list: &[Vec<usize>], community_of: &[usize]) -> f64 {
+    let num_community = community_of.iter().max().map_or(0, |&max| max + 1);
+    let mut inside = vec![0.0; num_community];
+    let mut degree = vec![0.0; num_community];
+    let mut total_degree = 0.0;
+    for (i, row) in adj_list.iter().enumerate() {
+        degree[community_of[i]] += row.len() as f64;
+        total_degree += row.len() as f64;
+        for &j in row {
+            if community_of[i] == community_of[j] {
+                inside[community_of[i]] += 1.0;
+            }
+        }
+    }
+    if total_degree == 0.0 {
+        return 0.0;
+    }
+    (0..num_community)
+        .map(|c| inside[c] / total_degree - (degree[c] / total_degree).powi(2))
+        .sum()
+}
+
+/// Normalized mutual information 2 I(X; Y) / (H(X) + H(Y)) between two partitions of the same nodes;
+/// 1 when both put every node together.
+pub fn normalized_mutual_information(first: &[usize], second: &[usize]) -> f64 {
+    let n = first.len() as f64;
+    let size = |partition: &[usize]| partition.iter().max().map_or(0, |&max| max + 1);
+    let (size_first, size_second) = (size(first), size(second));
+    let mut joint = vec![vec![0.0; size_second]; size_first];
+    let mut marginal_first = vec![0.0; size_first];
+    let mut marginal_second = vec![0.0; size_second];
+    for (&a, &b) in first.iter().zip(second.iter()) {
+        joint[a][b] += 1.0;
+        marginal_first[a] += 1.0;
+        marginal_second[b] += 1.0;
+    }
+
+    let entropy = |marginal: &[f64]| -> f64 {
+        marginal.iter().filter(|&&count| count > 0.0).map(|&count| -(count / n) * (count / n).ln()).sum()
+    };
+    let entropy_sum = entropy(&marginal_first) + entropy(&marginal_second);
+    if entropy_sum == 0.0 {
+        return 1.0;
+    }
+    let mut mutual_information = 0.0;
+    for a in 0..size_first {
+        for b in 0..size_second {
+            if joint[a][b] > 0.0 {
+                mutual_information += (joint[a][b] / n) * (joint[a][b] * n / (marginal_first[a] * marginal_second[b])).ln();
+            }
+        }
+    }
+    2.0 * mutual_information / entropy_sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    /// Two cliques of four joined by the tie 3-4.
+    fn two_cliques() -> Vec<Vec<usize>> {
+        let mut adj_list = vec![Vec::new(); 8];
+        for clique in [0..4, 4..8] {
+            for i in clique.clone() {
+                adj_list[i].extend(clique.clone().filter(|&j| j != i));
+            }
+        }
+        adj_list[3].push(4);
+        adj_list[4].push(3);
+        adj_list
+    }
+
+    #[test]
+    fn louvain_separates_two_joined_cliques() {
+        let cliques = [0, 0, 0, 0, 1, 1, 1, 1];
+        // 13 ties; each clique holds 6 of them and 13 of the 26 tie ends.
+        let expected = 2.0 * (12.0 / 26.0 - 0.25);
+        assert!((modularity(&two_cliques(), &cliques) - expected).abs() < 1e-12);
+        for seed in 0..10 {
+            let partition = louvain(&two_cliques(), &mut ScenarioRng::seed_from_u64(seed));
+            assert_eq!(partition.num_community, 2);
+            assert!((partition.modularity - expected).abs() < 1e-12);
+            assert!((normalized_mutual_information(&partition.community_of, &cliques) - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn modularity_is_zero_without_ties() {
+        assert_eq!(modularity(&[Vec::new(), Vec::new()], &[0, 1]), 0.0);
+        assert_eq!(louvain(&[Vec::new(), Vec::new()], &mut ScenarioRng::seed_from_u64(0)).modularity, 0.0);
+    }
+
+    #[test]
+    fn normalized_mutual_information_of_known_partitions() {
+        // The same partition under other labels, independent partitions, and one nested in the other:
+        // I = ln 2 and H = ln 2 + 2 ln 2, so NMI = 2 / 3.
+        assert!((normalized_mutual_information(&[0, 0, 1, 1], &[1, 1, 0, 0]) - 1.0).abs() < 1e-12);
+        assert!(normalized_mutual_information(&[0, 0, 1, 1], &[0, 1, 0, 1]).abs() < 1e-12);
+        assert!((normalized_mutual_information(&[0, 0, 1, 1], &[0, 1, 2, 3]) - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(normalized_mutual_information(&[0, 0, 0], &[0, 0, 0]), 1.0);
+    }
+}
@@ -1,650 +1,214 @@
 use rayon::prelude::*; 
-use ndarray::{ArrayD, Dim, IxDyn, IxDynImpl};
-use std::sync::{Arc, Mutex};
+use ndarray::{ArrayD, Dim, IxDyn, IxDynImpl, Zip};
+use std::io;
+use std::sync::Arc;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use chrono::Local;
-use crate::params::{self, GET_GRAPH_INIT, ITERATION};
+use serde::{Deserialize, Serialize};
+use crate::checkpoint::Checkpoint;
+use crate::metric::{self, Metric, METRICS};
+use crate::params::{ExperimentConfig, ARM_NR, ARM_RR, ARM_SC};
 use crate::scenario::Scenario;
 
 /// Manages the experiment, including running the experiment and processing results.
 /// Modify as needed based on your experiment design.
 pub struct ExperimentManager {
-    pub r_perf_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_nr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_nr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_rr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_rr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_12_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_12_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_23_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_23_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_13_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_perf_13_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_12_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_12_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_23_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_23_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_13_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_13_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_nr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_nr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_rr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_clws_rr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_12_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_12_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_23_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_23_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_13_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_13_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_nr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_nr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_rr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_cent_rr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_12_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_12_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_23_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_23_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_13_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_13_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_nr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_nr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_rr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_tria_rr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_12_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_12_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_23_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_23_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_13_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_13_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_nr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_nr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_rr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_effi_rr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_12_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_12_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_23_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_23_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_13_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_13_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_nr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_nr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_rr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_sigm_rr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_12_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_12_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_23_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_23_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_13_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_13_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_nr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_nr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_rr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_omeg_rr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_12_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_12_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_23_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_23_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_13_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_13_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_nr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_nr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_rr_avg: Arc::<Mutex<ndarray::ArrayD<f64>>>,
-    pub r_spva_rr_std: Arc::<Mutex<ndarray::ArrayD<f64>>>,
+    pub config: Arc<ExperimentConfig>,
+    /// The entries of `METRICS` recorded under this design.
+    pub metrics: Vec<&'static Metric>,
+    /// Dataset base name of each entry of `results`: every metric in every series, metric-major.
+    pub outcome_names: Vec<String>,
+    pub results: Vec<OutcomeVariable>,
+    /// Iterations already accumulated into `results`, in the order they were finished.
+    pub finished: Vec<usize>,
 }
 
 
 impl ExperimentManager {
 
-    pub fn new() -> Self{
-        let shape: ndarray::Dim<ndarray::IxDynImpl> = IxDyn(&params::RESULT_SHAPE);
-        ExperimentManager {
-            r_perf_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_nr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_nr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_rr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_rr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_12_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_12_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_23_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_23_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_13_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_perf_13_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_12_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_12_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_23_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_23_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_13_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_13_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_nr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_nr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_rr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_clws_rr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_12_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_12_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_23_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_23_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_13_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_13_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_nr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_nr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_rr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_cent_rr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_12_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_12_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_23_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_23_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_13_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_13_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_nr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_nr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_rr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_tria_rr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_12_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_12_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_23_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_23_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_13_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_13_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_nr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_nr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_rr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_effi_rr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_12_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_12_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_23_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_23_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_13_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_13_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_nr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_nr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_rr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_sigm_rr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_12_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_12_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_23_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_23_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_13_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_13_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_nr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_nr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_rr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_omeg_rr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_12_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_12_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_23_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_23_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_13_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_13_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_nr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_nr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_rr_avg: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
-            r_spva_rr_std: Arc::new(Mutex::new(ArrayD::zeros(shape.clone()))),
+    pub fn new(config: Arc<ExperimentConfig>) -> Self{
+        let series = metric::series();
+        let metrics: Vec<&'static Metric> = METRICS.iter().filter(|metric| metric.is_recorded(&config)).collect();
+        let mut outcome_names = Vec::with_capacity(metrics.len() * series.len());
+        for metric in metrics.iter() {
+            for series in series.iter() {
+                outcome_names.push(metric::outcome_name(metric, series));
+            }
         }
+        let mut experiment_manager = ExperimentManager {
+            config,
+            metrics,
+            outcome_names,
+            results: Vec::new(),
+            finished: Vec::new(),
+        };
+        experiment_manager.results = experiment_manager.empty_results();
+        experiment_manager
     }
 
-    pub fn run_experiments(&mut self) {
+    /// One empty accumulator per entry of `outcome_names`.
+    fn empty_results(&self) -> Vec<OutcomeVariable> {
+        let num_series = metric::series().len();
+        self.metrics
+            .iter()
+            .flat_map(|metric| (0..num_series).map(|_| OutcomeVariable::new(&metric.shape(&self.config))))
+            .collect()
+    }
+
+    /// Continues from the partial results of a checkpoint saved under the same design.
+    pub fn restore(&mut self, checkpoint: Checkpoint) -> io::Result<()> {
+        checkpoint.check_design(&self.config)?;
+        if checkpoint.outcome_names != self.outcome_names {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the checkpoint records a different set of outcomes"));
+        }
+        self.results = checkpoint.results;
+        self.finished = checkpoint.finished;
+        Ok(())
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            design: (*self.config).clone(),
+            finished: self.finished.clone(),
+            outcome_names: self.outcome_names.clone(),
+            results: self.results.clone(),
+        }
+    }
+
+    /// Runs every iteration of the shard not yet in `finished`, in batches of `checkpoint_interval`
+    /// iterations, saving a checkpoint after each batch.
+    pub fn run_experiments(&mut self) -> io::Result<()> {
+        let mut is_finished = vec![false; self.config.iteration];
+        for &iteration in self.finished.iter() {
+            is_finished[iteration] = true;
+        }
+        let shard = self.config.shard;
+        let num_iterations = (0..self.config.iteration).filter(|&iteration| shard.contains(iteration)).count();
+        let remaining: Vec<usize> = (0..self.config.iteration)
+            .filter(|&iteration| shard.contains(iteration) && !is_finished[iteration])
+            .collect();
+        let batch_size = match self.config.checkpoint_interval {
+            0 => remaining.len().max(1),
+            interval => interval,
+        };
+
         // Iterate over each combination in parallel
         let pb_multi = MultiProgress::new();
-        let pb_global = pb_multi.add(ProgressBar::new(params::PARAMS_INDEX_COMBINATIONS.get().unwrap().len() as u64 * ITERATION as u64));
+        let pb_global = pb_multi.add(ProgressBar::new(self.config.params_index_combinations.len() as u64 * num_iterations as u64));
+        pb_global.set_position(self.config.params_index_combinations.len() as u64 * self.finished.len() as u64);
         pb_global.set_style(
             ProgressStyle::default_bar()
                 .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.green/red}] {pos}/{len} ({eta_precise})")
                 .expect("Failed to set progress bar template")
                 .progress_chars("#>-"),
         );
+        let series = metric::series();
 
-        (0..params::ITERATION).into_par_iter().for_each(|_iter_idx: usize| {
-            let pb_local = pb_multi.add(ProgressBar::new(params::PARAMS_INDEX_COMBINATIONS.get().unwrap().len() as u64));
-            pb_local.set_style(ProgressStyle::with_template("{prefix:.bold.dim} [{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}").unwrap());
-            pb_local.set_prefix(format!("Thread {:?}", std::thread::current().id()));
-
-            let mut local_perf = OutcomeVariable::new();
-            let mut local_perf_rr = OutcomeVariable::new();
-            let mut local_perf_nr = OutcomeVariable::new();
-            let mut local_perf_12 = OutcomeVariable::new();
-            let mut local_perf_23 = OutcomeVariable::new();
-            let mut local_perf_13 = OutcomeVariable::new();
-
-            let mut local_clws = OutcomeVariable::new();
-            let mut local_clws_rr = OutcomeVariable::new();
-            let mut local_clws_nr = OutcomeVariable::new();
-            let mut local_clws_12 = OutcomeVariable::new();
-            let mut local_clws_23 = OutcomeVariable::new();
-            let mut local_clws_13 = OutcomeVariable::new();
-            
-            let mut local_cent = OutcomeVariable::new();
-            let mut local_cent_rr = OutcomeVariable::new();
-            let mut local_cent_nr = OutcomeVariable::new();
-            let mut local_cent_12 = OutcomeVariable::new();
-            let mut local_cent_23 = OutcomeVariable::new();
-            let mut local_cent_13 = OutcomeVariable::new();
-            
-            let mut local_tria = OutcomeVariable::new();
-            let mut local_tria_rr = OutcomeVariable::new();
-            let mut local_tria_nr = OutcomeVariable::new();
-            let mut local_tria_12 = OutcomeVariable::new();
-            let mut local_tria_23 = OutcomeVariable::new();
-            let mut local_tria_13 = OutcomeVariable::new();
-
-            let mut local_effi = OutcomeVariable::new();
-            let mut local_effi_rr = OutcomeVariable::new();
-            let mut local_effi_nr = OutcomeVariable::new();
-            let mut local_effi_12 = OutcomeVariable::new();
-            let mut local_effi_23 = OutcomeVariable::new();
-            let mut local_effi_13 = OutcomeVariable::new();
-
-            let mut local_sigm = OutcomeVariable::new();
-            let mut local_sigm_rr = OutcomeVariable::new();
-            let mut local_sigm_nr = OutcomeVariable::new();
-            let mut local_sigm_12 = OutcomeVariable::new();
-            let mut local_sigm_23 = OutcomeVariable::new();
-            let mut local_sigm_13 = OutcomeVariable::new();
-
-            let mut local_omeg = OutcomeVariable::new();
-            let mut local_omeg_rr = OutcomeVariable::new();
-            let mut local_omeg_nr = OutcomeVariable::new();
-            let mut local_omeg_12 = OutcomeVariable::new();
-            let mut local_omeg_23 = OutcomeVariable::new();
-            let mut local_omeg_13 = OutcomeVariable::new();
-            
-            let mut local_spva = OutcomeVariable::new();
-            let mut local_spva_rr = OutcomeVariable::new();
-            let mut local_spva_nr = OutcomeVariable::new();
-            let mut local_spva_12 = OutcomeVariable::new();
-            let mut local_spva_23 = OutcomeVariable::new();
-            let mut local_spva_13 = OutcomeVariable::new();
-
-            params::PARAMS_INDEX_COMBINATIONS.get().unwrap().iter().for_each(
-                |(
-                i_social_dynamics, 
-                i_span, 
-                i_enforcement, 
-                i_turbulence, 
-                i_turnover)| {
-                    let indices = vec![
-                        *i_social_dynamics,
-                        *i_span,
-                        *i_enforcement,
-                        *i_turbulence,
-                        *i_turnover,
-                    ];
-                    let span = params::SPAN[*i_span];
-                    let enforcement = params::ENFORCEMENT[*i_enforcement];
-                    let turbulence_rate = params::TURBULENCE_RATE[*i_turbulence];
-                    let turnover_rate = params::TURNOVER_RATE[*i_turnover];
-                    let turbulence_interval = params::TURBULENCE_INTERVAL[*i_turbulence];
-                    let mut scenario = Scenario::new(
-                        *i_social_dynamics,
-                        span,
-                        enforcement,
-                        turbulence_rate,
-                        turnover_rate,
-                    );
-                    let mut scenario_random_rewiring = scenario.get_clone();
-                    scenario_random_rewiring.set_network_params(true, true);
-                    let mut scenario_no_rewiring = scenario.get_clone();
-                    scenario_no_rewiring.set_network_params(false, false);
-                    scenario.do_rewiring(params::INFORMAL_INITIAL_NUM, 0); // Systematically formed
-                    scenario_random_rewiring.do_rewiring(params::INFORMAL_INITIAL_NUM, 0); // Randomly formed
-
-                    for t in 0..params::TIME {
-                        let mut indices_t = indices.clone();
-                        indices_t.push(t);
-                        let ix_dyn = IxDyn(&indices_t);
-                        
-                        local_perf.set(&ix_dyn, scenario.performance_avg);
-                        local_perf_rr.set(&ix_dyn, scenario_random_rewiring.performance_avg);
-                        local_perf_nr.set(&ix_dyn, scenario_no_rewiring.performance_avg);
-                        local_perf_12.set(&ix_dyn, scenario.performance_avg - scenario_random_rewiring.performance_avg);
-                        local_perf_23.set(&ix_dyn, scenario_random_rewiring.performance_avg - scenario_no_rewiring.performance_avg);
-                        local_perf_13.set(&ix_dyn, scenario.performance_avg - scenario_no_rewiring.performance_avg);
-
-                        local_clws.set(&ix_dyn, scenario.global_clustering_watts_strogatz);
-                        local_clws_rr.set(&ix_dyn, scenario_random_rewiring.global_clustering_watts_strogatz);
-                        local_clws_nr.set(&ix_dyn, scenario_no_rewiring.global_clustering_watts_strogatz);
-                        local_clws_12.set(&ix_dyn, scenario.global_clustering_watts_strogatz - scenario_random_rewiring.global_clustering_watts_strogatz);
-                        local_clws_23.set(&ix_dyn, scenario_random_rewiring.global_clustering_watts_strogatz - scenario_no_rewiring.global_clustering_watts_strogatz);
-                        local_clws_13.set(&ix_dyn, scenario.global_clustering_watts_strogatz - scenario_no_rewiring.global_clustering_watts_strogatz);
-
-                        local_cent.set(&ix_dyn, scenario.closeness_centralization);
-                        local_cent_rr.set(&ix_dyn, scenario_random_rewiring.closeness_centralization);
-                        local_cent_nr.set(&ix_dyn, scenario_no_rewiring.closeness_centralization);
-                        local_cent_12.set(&ix_dyn, scenario.closeness_centralization - scenario_random_rewiring.closeness_centralization);
-                        local_cent_23.set(&ix_dyn, scenario_random_rewiring.closeness_centralization - scenario_no_rewiring.closeness_centralization);
-                        local_cent_13.set(&ix_dyn, scenario.closeness_centralization - scenario_no_rewiring.closeness_centralization);
-
-                        local_tria.set(&ix_dyn, scenario.triadic_centralization);
-                        local_tria_rr.set(&ix_dyn, scenario_random_rewiring.triadic_centralization);
-                        local_tria_nr.set(&ix_dyn, scenario_no_rewiring.triadic_centralization);
-                        local_tria_12.set(&ix_dyn, scenario.triadic_centralization - scenario_random_rewiring.triadic_centralization);
-                        local_tria_23.set(&ix_dyn, scenario_random_rewiring.triadic_centralization - scenario_no_rewiring.triadic_centralization);
-                        local_tria_13.set(&ix_dyn, scenario.triadic_centralization - scenario_no_rewiring.triadic_centralization);
-
-                        local_spva.set(&ix_dyn, scenario.shortest_path_variance);
-                        local_spva_rr.set(&ix_dyn, scenario_random_rewiring.shortest_path_variance);
-                        local_spva_nr.set(&ix_dyn, scenario_no_rewiring.shortest_path_variance);
-                        local_spva_12.set(&ix_dyn, scenario.shortest_path_variance - scenario_random_rewiring.shortest_path_variance);
-                        local_spva_23.set(&ix_dyn, scenario_random_rewiring.shortest_path_variance - scenario_no_rewiring.shortest_path_variance);
-                        local_spva_13.set(&ix_dyn, scenario.shortest_path_variance - scenario_no_rewiring.shortest_path_variance);
-                        
-                        local_effi.set(&ix_dyn, scenario.network_efficiency);
-                        local_effi_rr.set(&ix_dyn, scenario_random_rewiring.network_efficiency);
-                        local_effi_nr.set(&ix_dyn, scenario_no_rewiring.network_efficiency);
-                        local_effi_12.set(&ix_dyn, scenario.network_efficiency - scenario_random_rewiring.network_efficiency);
-                        local_effi_23.set(&ix_dyn, scenario_random_rewiring.network_efficiency - scenario_no_rewiring.network_efficiency);
-                        local_effi_13.set(&ix_dyn, scenario.network_efficiency - scenario_no_rewiring.network_efficiency);
-
-                        local_sigm.set(&ix_dyn, scenario.omega);
-                        local_sigm_rr.set(&ix_dyn, scenario_random_rewiring.omega);
-                        local_sigm_nr.set(&ix_dyn, scenario_no_rewiring.omega);
-                        local_sigm_12.set(&ix_dyn, scenario.omega - scenario_random_rewiring.omega);
-                        local_sigm_23.set(&ix_dyn, scenario_random_rewiring.omega - scenario_no_rewiring.omega);
-                        local_sigm_13.set(&ix_dyn, scenario.omega - scenario_no_rewiring.omega);
-                        
-                        local_omeg.set(&ix_dyn, scenario.omega);
-                        local_omeg_rr.set(&ix_dyn, scenario_random_rewiring.omega);
-                        local_omeg_nr.set(&ix_dyn, scenario_no_rewiring.omega);
-                        local_omeg_12.set(&ix_dyn, scenario.omega - scenario_random_rewiring.omega);
-                        local_omeg_23.set(&ix_dyn, scenario_random_rewiring.omega - scenario_no_rewiring.omega);
-                        local_omeg_13.set(&ix_dyn, scenario.omega - scenario_no_rewiring.omega);
-
-                        scenario.step_forward();
-                        scenario_random_rewiring.step_forward();
-                        scenario_no_rewiring.step_forward();
-                        if t % turbulence_interval == 0 {
-                            scenario.do_turbulence();
-                            scenario_random_rewiring.do_turbulence();
-                            scenario_no_rewiring.do_turbulence();
-                        }
-                    }
-                    pb_local.inc(1);
-                    pb_global.inc(1);
-            });
+        for batch in remaining.chunks(batch_size) {
+            // Each rayon worker folds its iterations into its own accumulators; these are merged
+            // pairwise once the batch is done, so no lock is shared between workers.
+            let results = batch.par_iter().fold(|| self.empty_results(), |mut local_results, &iteration: &usize| {
+                let pb_local = pb_multi.add(ProgressBar::new(self.config.params_index_combinations.len() as u64));
+                pb_local.set_style(ProgressStyle::with_template("{prefix:.bold.dim} [{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}").unwrap());
+                pb_local.set_prefix(format!("Thread {:?}", std::thread::current().id()));
 
-            params::PARAMS_INDEX_COMBINATIONS.get().unwrap().iter().for_each(
-                |(
+                self.config.params_index_combinations.iter().enumerate().for_each(
+                    |(i_combination, (
                     i_social_dynamics, 
                     i_span, 
                     i_enforcement, 
                     i_turbulence, 
-                    i_turnover)| {
-                        for t in 0..params::TIME {
-                            let ix_dyn = IxDyn(&vec![
-                                *i_social_dynamics,
-                                *i_span,
-                                *i_enforcement,
-                                *i_turbulence,
-                                *i_turnover,
-                                t
-                            ]);
-                            self.r_perf_avg.lock().unwrap()[&ix_dyn] += local_perf.avg[&ix_dyn];
-                            self.r_perf_std.lock().unwrap()[&ix_dyn] += local_perf.std[&ix_dyn];
-                            self.r_perf_rr_avg.lock().unwrap()[&ix_dyn] += local_perf_rr.avg[&ix_dyn];
-                            self.r_perf_rr_std.lock().unwrap()[&ix_dyn] += local_perf_rr.std[&ix_dyn];
-                            self.r_perf_nr_avg.lock().unwrap()[&ix_dyn] += local_perf_nr.avg[&ix_dyn];
-                            self.r_perf_nr_std.lock().unwrap()[&ix_dyn] += local_perf_nr.std[&ix_dyn];
-                            self.r_perf_12_avg.lock().unwrap()[&ix_dyn] += local_perf_12.avg[&ix_dyn];
-                            self.r_perf_12_std.lock().unwrap()[&ix_dyn] += local_perf_12.std[&ix_dyn];
-                            self.r_perf_23_avg.lock().unwrap()[&ix_dyn] += local_perf_23.avg[&ix_dyn];
-                            self.r_perf_23_std.lock().unwrap()[&ix_dyn] += local_perf_23.std[&ix_dyn];
-                            self.r_perf_13_avg.lock().unwrap()[&ix_dyn] += local_perf_13.avg[&ix_dyn];
-                            self.r_perf_13_std.lock().unwrap()[&ix_dyn] += local_perf_13.std[&ix_dyn];
-                            
-                            self.r_clws_avg.lock().unwrap()[&ix_dyn] += local_clws.avg[&ix_dyn];
-                            self.r_clws_std.lock().unwrap()[&ix_dyn] += local_clws.std[&ix_dyn];
-                            self.r_clws_rr_avg.lock().unwrap()[&ix_dyn] += local_clws_rr.avg[&ix_dyn];
-                            self.r_clws_rr_std.lock().unwrap()[&ix_dyn] += local_clws_rr.std[&ix_dyn];
-                            self.r_clws_nr_avg.lock().unwrap()[&ix_dyn] += local_clws_nr.avg[&ix_dyn];
-                            self.r_clws_nr_std.lock().unwrap()[&ix_dyn] += local_clws_nr.std[&ix_dyn];
-                            self.r_clws_12_avg.lock().unwrap()[&ix_dyn] += local_clws_12.avg[&ix_dyn];
-                            self.r_clws_12_std.lock().unwrap()[&ix_dyn] += local_clws_12.std[&ix_dyn];
-                            self.r_clws_23_avg.lock().unwrap()[&ix_dyn] += local_clws_23.avg[&ix_dyn];
-                            self.r_clws_23_std.lock().unwrap()[&ix_dyn] += local_clws_23.std[&ix_dyn];
-                            self.r_clws_13_avg.lock().unwrap()[&ix_dyn] += local_clws_13.avg[&ix_dyn];
-                            self.r_clws_13_std.lock().unwrap()[&ix_dyn] += local_clws_13.std[&ix_dyn];
-                            
-                            self.r_cent_avg.lock().unwrap()[&ix_dyn] += local_cent.avg[&ix_dyn];
-                            self.r_cent_std.lock().unwrap()[&ix_dyn] += local_cent.std[&ix_dyn];
-                            self.r_cent_rr_avg.lock().unwrap()[&ix_dyn] += local_cent_rr.avg[&ix_dyn];
-                            self.r_cent_rr_std.lock().unwrap()[&ix_dyn] += local_cent_rr.std[&ix_dyn];
-                            self.r_cent_nr_avg.lock().unwrap()[&ix_dyn] += local_cent_nr.avg[&ix_dyn];
-                            self.r_cent_nr_std.lock().unwrap()[&ix_dyn] += local_cent_nr.std[&ix_dyn];
-                            self.r_cent_12_avg.lock().unwrap()[&ix_dyn] += local_cent_12.avg[&ix_dyn];
-                            self.r_cent_12_std.lock().unwrap()[&ix_dyn] += local_cent_12.std[&ix_dyn];
-                            self.r_cent_23_avg.lock().unwrap()[&ix_dyn] += local_cent_23.avg[&ix_dyn];
-                            self.r_cent_23_std.lock().unwrap()[&ix_dyn] += local_cent_23.std[&ix_dyn];
-                            self.r_cent_13_avg.lock().unwrap()[&ix_dyn] += local_cent_13.avg[&ix_dyn];
-                            self.r_cent_13_std.lock().unwrap()[&ix_dyn] += local_cent_13.std[&ix_dyn];
-                            
-                            self.r_tria_avg.lock().unwrap()[&ix_dyn] += local_tria.avg[&ix_dyn];
-                            self.r_tria_std.lock().unwrap()[&ix_dyn] += local_tria.std[&ix_dyn];
-                            self.r_tria_rr_avg.lock().unwrap()[&ix_dyn] += local_tria_rr.avg[&ix_dyn];
-                            self.r_tria_rr_std.lock().unwrap()[&ix_dyn] += local_tria_rr.std[&ix_dyn];
-                            self.r_tria_nr_avg.lock().unwrap()[&ix_dyn] += local_tria_nr.avg[&ix_dyn];
-                            self.r_tria_nr_std.lock().unwrap()[&ix_dyn] += local_tria_nr.std[&ix_dyn];
-                            self.r_tria_12_avg.lock().unwrap()[&ix_dyn] += local_tria_12.avg[&ix_dyn];
-                            self.r_tria_12_std.lock().unwrap()[&ix_dyn] += local_tria_12.std[&ix_dyn];
-                            self.r_tria_23_avg.lock().unwrap()[&ix_dyn] += local_tria_23.avg[&ix_dyn];
-                            self.r_tria_23_std.lock().unwrap()[&ix_dyn] += local_tria_23.std[&ix_dyn];
-                            self.r_tria_13_avg.lock().unwrap()[&ix_dyn] += local_tria_13.avg[&ix_dyn];
-                            self.r_tria_13_std.lock().unwrap()[&ix_dyn] += local_tria_13.std[&ix_dyn];
-                            
-                            self.r_spva_avg.lock().unwrap()[&ix_dyn] += local_spva.avg[&ix_dyn];
-                            self.r_spva_std.lock().unwrap()[&ix_dyn] += local_spva.std[&ix_dyn];
-                            self.r_spva_rr_avg.lock().unwrap()[&ix_dyn] += local_spva_rr.avg[&ix_dyn];
-                            self.r_spva_rr_std.lock().unwrap()[&ix_dyn] += local_spva_rr.std[&ix_dyn];
-                            self.r_spva_nr_avg.lock().unwrap()[&ix_dyn] += local_spva_nr.avg[&ix_dyn];
-                            self.r_spva_nr_std.lock().unwrap()[&ix_dyn] += local_spva_nr.std[&ix_dyn];
-                            self.r_spva_12_avg.lock().unwrap()[&ix_dyn] += local_spva_12.avg[&ix_dyn];
-                            self.r_spva_12_std.lock().unwrap()[&ix_dyn] += local_spva_12.std[&ix_dyn];
-                            self.r_spva_23_avg.lock().unwrap()[&ix_dyn] += local_spva_23.avg[&ix_dyn];
-                            self.r_spva_23_std.lock().unwrap()[&ix_dyn] += local_spva_23.std[&ix_dyn];
-                            self.r_spva_13_avg.lock().unwrap()[&ix_dyn] += local_spva_13.avg[&ix_dyn];
-                            self.r_spva_13_std.lock().unwrap()[&ix_dyn] += local_spva_13.std[&ix_dyn];
-                            
-                            self.r_effi_avg.lock().unwrap()[&ix_dyn] += local_effi.avg[&ix_dyn];
-                            self.r_effi_std.lock().unwrap()[&ix_dyn] += local_effi.std[&ix_dyn];
-                            self.r_effi_rr_avg.lock().unwrap()[&ix_dyn] += local_effi_rr.avg[&ix_dyn];
-                            self.r_effi_rr_std.lock().unwrap()[&ix_dyn] += local_effi_rr.std[&ix_dyn];
-                            self.r_effi_nr_avg.lock().unwrap()[&ix_dyn] += local_effi_nr.avg[&ix_dyn];
-                            self.r_effi_nr_std.lock().unwrap()[&ix_dyn] += local_effi_nr.std[&ix_dyn];
-                            self.r_effi_12_avg.lock().unwrap()[&ix_dyn] += local_effi_12.avg[&ix_dyn];
-                            self.r_effi_12_std.lock().unwrap()[&ix_dyn] += local_effi_12.std[&ix_dyn];
-                            self.r_effi_23_avg.lock().unwrap()[&ix_dyn] += local_effi_23.avg[&ix_dyn];
-                            self.r_effi_23_std.lock().unwrap()[&ix_dyn] += local_effi_23.std[&ix_dyn];
-                            self.r_effi_13_avg.lock().unwrap()[&ix_dyn] += local_effi_13.avg[&ix_dyn];
-                            self.r_effi_13_std.lock().unwrap()[&ix_dyn] += local_effi_13.std[&ix_dyn];
-                            
-                            self.r_sigm_avg.lock().unwrap()[&ix_dyn] += local_sigm.avg[&ix_dyn];
-                            self.r_sigm_std.lock().unwrap()[&ix_dyn] += local_sigm.std[&ix_dyn];
-                            self.r_sigm_rr_avg.lock().unwrap()[&ix_dyn] += local_sigm_rr.avg[&ix_dyn];
-                            self.r_sigm_rr_std.lock().unwrap()[&ix_dyn] += local_sigm_rr.std[&ix_dyn];
-                            self.r_sigm_nr_avg.lock().unwrap()[&ix_dyn] += local_sigm_nr.avg[&ix_dyn];
-                            self.r_sigm_nr_std.lock().unwrap()[&ix_dyn] += local_sigm_nr.std[&ix_dyn];
-                            self.r_sigm_12_avg.lock().unwrap()[&ix_dyn] += local_sigm_12.avg[&ix_dyn];
-                            self.r_sigm_12_std.lock().unwrap()[&ix_dyn] += local_sigm_12.std[&ix_dyn];
-                            self.r_sigm_23_avg.lock().unwrap()[&ix_dyn] += local_sigm_23.avg[&ix_dyn];
-                            self.r_sigm_23_std.lock().unwrap()[&ix_dyn] += local_sigm_23.std[&ix_dyn];
-                            self.r_sigm_13_avg.lock().unwrap()[&ix_dyn] += local_sigm_13.avg[&ix_dyn];
-                            self.r_sigm_13_std.lock().unwrap()[&ix_dyn] += local_sigm_13.std[&ix_dyn];
-            
-                            self.r_omeg_avg.lock().unwrap()[&ix_dyn] += local_omeg.avg[&ix_dyn];
-                            self.r_omeg_std.lock().unwrap()[&ix_dyn] += local_omeg.std[&ix_dyn];
-                            self.r_omeg_rr_avg.lock().unwrap()[&ix_dyn] += local_omeg_rr.avg[&ix_dyn];
-                            self.r_omeg_rr_std.lock().unwrap()[&ix_dyn] += local_omeg_rr.std[&ix_dyn];
-                            self.r_omeg_nr_avg.lock().unwrap()[&ix_dyn] += local_omeg_nr.avg[&ix_dyn];
-                            self.r_omeg_nr_std.lock().unwrap()[&ix_dyn] += local_omeg_nr.std[&ix_dyn];
-                            self.r_omeg_12_avg.lock().unwrap()[&ix_dyn] += local_omeg_12.avg[&ix_dyn];
-                            self.r_omeg_12_std.lock().unwrap()[&ix_dyn] += local_omeg_12.std[&ix_dyn];
-                            self.r_omeg_23_avg.lock().unwrap()[&ix_dyn] += local_omeg_23.avg[&ix_dyn];
-                            self.r_omeg_23_std.lock().unwrap()[&ix_dyn] += local_omeg_23.std[&ix_dyn];
-                            self.r_omeg_13_avg.lock().unwrap()[&ix_dyn] += local_omeg_13.avg[&ix_dyn];
-                            self.r_omeg_13_std.lock().unwrap()[&ix_dyn] += local_omeg_13.std[&ix_dyn];
-                        }
-                    }
-            );
-            pb_local.finish_and_clear();
-        });
-        params::PARAMS_INDEX_COMBINATIONS.get().unwrap().iter().for_each(
-            |(
-                i_social_dynamics, 
-                i_span, 
-                i_enforcement, 
-                i_turbulence, 
-                i_turnover)| {
-                    for t in 0..params::TIME {
-                        let ix_dyn = IxDyn(&vec![
+                    i_turnover))| {
+                        let indices = vec![
                             *i_social_dynamics,
                             *i_span,
                             *i_enforcement,
                             *i_turbulence,
                             *i_turnover,
-                            t
-                        ]);
-                        self.r_perf_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_rr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_rr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_nr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_nr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_12_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_12_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_23_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_23_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_13_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_perf_13_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        
-                        self.r_clws_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_rr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_rr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_nr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_nr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_12_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_12_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_23_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_23_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_13_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_clws_13_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        
-                        self.r_cent_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_rr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_rr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_nr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_nr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_12_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_12_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_23_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_23_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_13_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_cent_13_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        
-                        self.r_tria_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_rr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_rr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_nr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_nr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_12_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_12_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_23_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_23_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_13_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_tria_13_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        
-                        self.r_spva_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_rr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_rr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_nr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_nr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_12_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_12_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_23_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_23_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_13_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_spva_13_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        
-                        self.r_effi_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_rr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_rr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_nr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_nr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_12_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_12_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_23_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_23_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_13_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_effi_13_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        
-                        self.r_sigm_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_rr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_rr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_nr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_nr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_12_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_12_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_23_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_23_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_13_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_sigm_13_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-        
-                        self.r_omeg_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_rr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_rr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_nr_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_nr_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_12_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_12_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_23_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_23_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_13_avg.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
-                        self.r_omeg_13_std.lock().unwrap()[&ix_dyn] /= ITERATION as f64;
+                        ];
+                        let span = self.config.span[*i_span];
+                        let enforcement = self.config.enforcement[*i_enforcement];
+                        let turbulence_rate = self.config.turbulence_rate[*i_turbulence];
+                        let turnover_rate = self.config.turnover_rate[*i_turnover];
+                        let turbulence_interval = self.config.turbulence_interval[*i_turbulence];
+                        let mut scenario = Scenario::new(
+                            &self.config,
+                            self.config.rng_for(iteration, i_combination, ARM_SC),
+                            self.config.social_dynamics_at(*i_social_dynamics),
+                            span,
+                            enforcement,
+                            turbulence_rate,
+                            turnover_rate,
+                        );
+                        let mut scenario_random_rewiring = scenario.get_clone(self.config.rng_for(iteration, i_combination, ARM_RR));
+                        scenario_random_rewiring.set_network_params(true, true);
+                        let mut scenario_no_rewiring = scenario.get_clone(self.config.rng_for(iteration, i_combination, ARM_NR));
+                        scenario_no_rewiring.set_network_params(false, false);
+                        scenario.do_rewiring(self.config.informal_initial_num, 0); // Systematically formed
+                        scenario_random_rewiring.do_rewiring(self.config.informal_initial_num, 0); // Randomly formed
+
+                        // Indexed by ARM_SC, ARM_RR and ARM_NR
+                        let mut arms = [scenario, scenario_random_rewiring, scenario_no_rewiring];
+
+                        for t in 0..self.config.time {
+                            for (i_metric, metric) in self.metrics.iter().enumerate() {
+                                let num_layers = metric.num_layers(&self.config);
+                                let num_bins = metric.num_bins(&self.config);
+                                for layer in 0..num_layers {
+                                    for bin in 0..num_bins {
+                                        let mut indices_t = indices.clone();
+                                        if num_layers > 1 {
+                                            indices_t.push(layer);
+                                        }
+                                        indices_t.push(t);
+                                        if num_bins > 1 {
+                                            indices_t.push(bin);
+                                        }
+                                        let ix_dyn = IxDyn(&indices_t);
+
+                                        let by_arm = arms.each_ref().map(|scenario| metric.value(scenario, layer, bin));
+                                        for (i_series, one_series) in series.iter().enumerate() {
+                                            local_results[i_metric * series.len() + i_series].push(&ix_dyn, one_series.value(&by_arm));
+                                        }
+                                    }
+                                }
+                            }
 
+                            for scenario in arms.iter_mut() {
+                                scenario.step_forward();
+                                if t % turbulence_interval == 0 {
+                                    scenario.do_turbulence();
+                                }
+                            }
                         }
-                    });
+                        pb_local.inc(1);
+                        pb_global.inc(1);
+                });
 
+                pb_local.finish_and_clear();
+                local_results
+            }).reduce(|| self.empty_results(), |mut results, other| {
+                for (result, other) in results.iter_mut().zip(other.iter()) {
+                    result.merge(other);
+                }
+                results
+            });
+            for (result, batch_result) in self.results.iter_mut().zip(results.iter()) {
+                result.merge(batch_result);
+            }
+            self.finished.extend_from_slice(batch);
+            if self.config.checkpoint_interval > 0 {
+                self.checkpoint().save(self.config.checkpoint_file_path())?;
+            }
+        }
         // CLEAN RESULTS HERE //
         pb_global.finish_with_message("Done!");
+        Ok(())
     }
 
     pub fn sample_network_csv(&self){
                 // Iterate over each combination in parallel
-                let length_combination = params::PARAMS_INDEX_COMBINATIONS.get().unwrap().len();
+                let length_combination = self.config.params_index_combinations.len();
                 let pb = ProgressBar::new(length_combination as u64);
                 pb.set_style(
                     ProgressStyle::default_bar()
@@ -658,45 +222,48 @@ impl ExperimentManager {
                     length_combination
                 );
                 
-                params::PARAMS_INDEX_COMBINATIONS.get().unwrap().into_par_iter().for_each(|(
+                // The samples follow the first iteration of `run_experiments` under the same seed.
+                self.config.params_index_combinations.par_iter().enumerate().for_each(|(i_combination, (
                     i_social_dynamics, 
                     i_span, 
                     i_enforcement, 
                     i_turbulence, 
                     i_turnover,
-                )| {
+                ))| {
                     // Create a new Scenario with the given parameters
-                    let span = params::SPAN[*i_span];
-                    let enforcement = params::ENFORCEMENT[*i_enforcement];
-                    let turbulence_rate = params::TURBULENCE_RATE[*i_turbulence];
-                    let turnover_rate = params::TURNOVER_RATE[*i_turnover];
-                    let turbulence_interval = params::TURBULENCE_INTERVAL[*i_turbulence];
+                    let span = self.config.span[*i_span];
+                    let enforcement = self.config.enforcement[*i_enforcement];
+                    let turbulence_rate = self.config.turbulence_rate[*i_turbulence];
+                    let turnover_rate = self.config.turnover_rate[*i_turnover];
+                    let turbulence_interval = self.config.turbulence_interval[*i_turbulence];
 
                     let mut scenario = Scenario::new(
-                        *i_social_dynamics,
+                        &self.config,
+                        self.config.rng_for(0, i_combination, ARM_SC),
+                        self.config.social_dynamics_at(*i_social_dynamics),
                         span,
                         enforcement,
                         turbulence_rate,
                         turnover_rate,
                     );
                     
-                    let mut scenario_random_rewiring = scenario.get_clone();
+                    let mut scenario_random_rewiring = scenario.get_clone(self.config.rng_for(0, i_combination, ARM_RR));
                     scenario_random_rewiring.set_network_params(true, true);
-                    let mut scenario_no_rewiring = scenario.get_clone();
+                    let mut scenario_no_rewiring = scenario.get_clone(self.config.rng_for(0, i_combination, ARM_NR));
                     scenario_no_rewiring.set_network_params(false, false);
-                    scenario.do_rewiring(params::INFORMAL_INITIAL_NUM, 0); // Systematically formed
-                    scenario_random_rewiring.do_rewiring(params::INFORMAL_INITIAL_NUM, 0); // Randomly formed
+                    scenario.do_rewiring(self.config.informal_initial_num, 0); // Systematically formed
+                    scenario_random_rewiring.do_rewiring(self.config.informal_initial_num, 0); // Randomly formed
     
-                    let file_name_network_csv = format!("{}s{}e{}ptb{}itb{}ptn{}.csv", if *i_social_dynamics==0 {"NetCl"} else {"PrfAt"}, span, enforcement, turbulence_rate, turbulence_interval, turnover_rate);
-                    let path_network_csv = (params::PARAM_STRING).clone();
+                    let file_name_network_csv = format!("{}s{}e{}ptb{}itb{}ptn{}.csv", self.config.social_dynamics_at(*i_social_dynamics).code(), span, enforcement, turbulence_rate, turbulence_interval, turnover_rate);
+                    let path_network_csv = self.config.sample_dir_path().display().to_string();
 
-                    if GET_GRAPH_INIT {
+                    if self.config.get_graph_init {
                         scenario.export_network_csv(format!("{}/{}_{}_t0", &path_network_csv, "sc", &file_name_network_csv).as_str());
                         scenario_random_rewiring.export_network_csv(format!("{}/{}_{}_t0", &path_network_csv, "rr", &file_name_network_csv).as_str());
                         scenario_no_rewiring.export_network_csv(format!("{}/{}_{}_t0", &path_network_csv, "nr", &file_name_network_csv).as_str());
                     }
                     
-                    for t in 0..params::TIME {
+                    for t in 0..self.config.time {
                         scenario.step_forward();
                         scenario_random_rewiring.step_forward();
                         scenario_no_rewiring.step_forward();
@@ -707,9 +274,9 @@ impl ExperimentManager {
                         }
                     }
 
-                    scenario.export_network_csv(format!("{}/{}_{}_t{}", &path_network_csv, "sc", &file_name_network_csv, params::TIME-1).as_str());
-                    scenario_random_rewiring.export_network_csv(format!("{}/{}_{}_t{}", &path_network_csv, "rr", &file_name_network_csv, params::TIME-1).as_str());
-                    scenario_no_rewiring.export_network_csv(format!("{}/{}_{}_t{}", &path_network_csv, "nr", &file_name_network_csv, params::TIME-1).as_str());
+                    scenario.export_network_csv(format!("{}/{}_{}_t{}", &path_network_csv, "sc", &file_name_network_csv, self.config.time-1).as_str());
+                    scenario_random_rewiring.export_network_csv(format!("{}/{}_{}_t{}", &path_network_csv, "rr", &file_name_network_csv, self.config.time-1).as_str());
+                    scenario_no_rewiring.export_network_csv(format!("{}/{}_{}_t{}", &path_network_csv, "nr", &file_name_network_csv, self.config.time-1).as_str());
 
                     pb.inc(1); // Increment the progress bar
                 });
@@ -720,25 +287,212 @@ impl ExperimentManager {
 }
 
 
-// Example struct for "Performance" metrics
-#[derive(Default)]
-struct OutcomeVariable {
-    avg: ArrayD<f64>,
-    std: ArrayD<f64>,
+/// z-score of a two-sided 95% interval under the normal approximation of the sample mean.
+const Z_95: f64 = 1.959_963_984_540_054;
+
+/// Streaming mean and variance of an outcome in every cell of `result_shape` (Welford).
+/// Accumulators filled on different threads are combined with `merge`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OutcomeVariable {
+    pub count: ArrayD<u64>,
+    #[serde(with = "crate::checkpoint::f64_bits")]
+    pub mean: ArrayD<f64>,
+    /// Sum of squared deviations from the mean.
+    #[serde(with = "crate::checkpoint::f64_bits")]
+    pub m2: ArrayD<f64>,
 }
 
-// Methods to accumulate values
 impl OutcomeVariable {
-    fn new() -> Self {
+    fn new(shape: &[usize]) -> Self {
         Self {
-            avg: ArrayD::zeros(IxDyn(&params::RESULT_SHAPE)),
-            std: ArrayD::zeros(IxDyn(&params::RESULT_SHAPE)),
+            count: ArrayD::zeros(IxDyn(shape)),
+            mean: ArrayD::zeros(IxDyn(shape)),
+            m2: ArrayD::zeros(IxDyn(shape)),
+        }
+    }
+
+    // Accumulate a single observation at time t
+    fn push(&mut self, ix_dyn: &Dim<IxDynImpl>, value: f64) {
+        self.count[ix_dyn] += 1;
+        let delta = value - self.mean[ix_dyn];
+        self.mean[ix_dyn] += delta / self.count[ix_dyn] as f64;
+        self.m2[ix_dyn] += delta * (value - self.mean[ix_dyn]);
+    }
+
+    /// Folds in the observations of another accumulator (Chan et al.).
+    pub fn merge(&mut self, other: &OutcomeVariable) {
+        Zip::from(&mut self.count)
+            .and(&mut self.mean)
+            .and(&mut self.m2)
+            .and(&other.count)
+            .and(&other.mean)
+            .and(&other.m2)
+            .for_each(|count, mean, m2, &count_other, &mean_other, &m2_other| {
+                if count_other == 0 {
+                    return;
+                }
+                let total = *count + count_other;
+                let delta = mean_other - *mean;
+                *mean += delta * count_other as f64 / total as f64;
+                *m2 += m2_other + delta.powi(2) * *count as f64 * count_other as f64 / total as f64;
+                *count = total;
+            });
+    }
+
+    /// Sample variance; NaN where fewer than two observations were made.
+    pub fn variance(&self) -> ArrayD<f64> {
+        Zip::from(&self.count).and(&self.m2).map_collect(|&count, &m2| {
+            if count < 2 { f64::NAN } else { m2 / (count - 1) as f64 }
+        })
+    }
+
+    pub fn std_dev(&self) -> ArrayD<f64> {
+        self.variance().mapv_into(f64::sqrt)
+    }
+
+    /// Standard error of the mean.
+    pub fn standard_error(&self) -> ArrayD<f64> {
+        Zip::from(&self.variance()).and(&self.count).map_collect(|&variance, &count| (variance / count as f64).sqrt())
+    }
+
+    /// Lower and upper bounds of the 95% confidence interval of the mean.
+    pub fn confidence_interval(&self) -> (ArrayD<f64>, ArrayD<f64>) {
+        let margin = self.standard_error() * Z_95;
+        (&self.mean - &margin, &self.mean + &margin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::Shard;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    /// A design small enough to run whole in a test.
+    fn config(iteration: usize) -> ExperimentConfig {
+        let mut config = ExperimentConfig {
+            seed: 11,
+            iteration,
+            time: 6,
+            n: 20,
+            social_dynamics: vec!["network_closure".to_string(), "homophily".to_string()],
+            span: vec![3],
+            turbulence_interval: vec![6],
+            checkpoint_interval: 0,
+            ..ExperimentConfig::default()
+        };
+        config.set_second_order_params();
+        config
+    }
+
+    fn run(config: ExperimentConfig) -> ExperimentManager {
+        let mut experiment_manager = ExperimentManager::new(Arc::new(config));
+        experiment_manager.run_experiments().unwrap();
+        experiment_manager
+    }
+
+    fn assert_same_bits(a: &[OutcomeVariable], b: &[OutcomeVariable]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b) {
+            assert_eq!(a.count, b.count);
+            assert_eq!(a.mean.mapv(f64::to_bits), b.mean.mapv(f64::to_bits));
+            assert_eq!(a.m2.mapv(f64::to_bits), b.m2.mapv(f64::to_bits));
+        }
+    }
+
+    /// Equal up to the order in which rayon happens to merge the accumulators of its workers.
+    fn assert_same_results(a: &[OutcomeVariable], b: &[OutcomeVariable]) {
+        let close = |a: f64, b: f64| (a.is_nan() && b.is_nan()) || (a - b).abs() <= 1e-9 * (1.0 + a.abs().max(b.abs()));
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b) {
+            assert_eq!(a.count, b.count);
+            assert!(Zip::from(&a.mean).and(&b.mean).all(|&a, &b| close(a, b)));
+            assert!(Zip::from(&a.m2).and(&b.m2).all(|&a, &b| close(a, b)));
+        }
+    }
+
+    #[test]
+    fn merged_accumulators_match_a_single_pass() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let values: Vec<f64> = (0..1000).map(|_| rng.random_range(-3.0..7.0)).collect();
+        let ix_dyn = IxDyn(&[0]);
+        let mut single = OutcomeVariable::new(&[1]);
+        for &value in values.iter() {
+            single.push(&ix_dyn, value);
+        }
+        // Uneven parts, one of them empty.
+        let mut merged = OutcomeVariable::new(&[1]);
+        for part in [&values[..1], &values[1..1], &values[1..380], &values[380..]] {
+            let mut accumulator = OutcomeVariable::new(&[1]);
+            for &value in part {
+                accumulator.push(&ix_dyn, value);
+            }
+            merged.merge(&accumulator);
+        }
+
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let m2: f64 = values.iter().map(|value| (value - mean).powi(2)).sum();
+        for accumulator in [&single, &merged] {
+            assert_eq!(accumulator.count[&ix_dyn], values.len() as u64);
+            assert!((accumulator.mean[&ix_dyn] - mean).abs() < 1e-12);
+            assert!((accumulator.m2[&ix_dyn] - m2).abs() < 1e-9 * m2);
         }
     }
 
-    // Accumulate a single value at time t
-    fn set(&mut self, ix_dyn:&Dim<IxDynImpl>, value: f64) {
-        self.avg[ix_dyn] = value;
-        self.std[ix_dyn] = value.powi(2);
+    #[test]
+    fn a_fixed_seed_reproduces_the_results() {
+        let first = run(config(3));
+        let second = run(config(3));
+        assert_eq!(first.finished.len(), 3);
+        assert_same_results(&first.results, &second.results);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn a_resumed_run_matches_an_uninterrupted_one() {
+        let output_dir = std::env::temp_dir().join(format!("flat-resume-{}", std::process::id()));
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let config = || {
+            let mut config = ExperimentConfig { checkpoint_interval: 1, output_dir: output_dir.clone(), ..config(3) };
+            config.set_second_order_params();
+            config
+        };
+        let uninterrupted = run(config());
+
+        // Interrupted after the first iteration: the others count as finished and are then forgotten.
+        let mut interrupted = ExperimentManager::new(Arc::new(config()));
+        interrupted.finished = vec![1, 2];
+        interrupted.run_experiments().unwrap();
+        let mut checkpoint = interrupted.checkpoint();
+        checkpoint.finished = vec![0];
+        checkpoint.save(config().checkpoint_file_path()).unwrap();
+
+        let mut resumed = ExperimentManager::new(Arc::new(config()));
+        resumed.restore(Checkpoint::load(config().checkpoint_file_path()).unwrap().unwrap()).unwrap();
+        resumed.run_experiments().unwrap();
+        std::fs::remove_dir_all(&output_dir).unwrap();
+
+        assert_eq!(resumed.finished, uninterrupted.finished);
+        assert_same_bits(&resumed.results, &uninterrupted.results);
+    }
+
+    #[test]
+    fn merged_shards_match_a_single_run() {
+        let whole = run(config(5));
+        let mut merged = ExperimentManager::new(Arc::new(config(5)));
+        let mut finished = Vec::new();
+        for index in 0..3 {
+            let mut config = ExperimentConfig { shard: Shard { index, count: 3 }, ..config(5) };
+            config.set_second_order_params();
+            let shard = run(config);
+            for (result, shard_result) in merged.results.iter_mut().zip(shard.results.iter()) {
+                result.merge(shard_result);
+            }
+            finished.extend(shard.finished);
+        }
+        finished.sort();
+
+        assert_eq!(finished, (0..5).collect::<Vec<usize>>());
+        assert_same_results(&merged.results, &whole.results);
+    }
+}
@@ -1,13 +1,29 @@
+use std::path::Path;
 use hdf5::File;
 use hdf5::dataset::Dataset;
-use crate::{experiment_manager::ExperimentManager, params};
+use hdf5::types::{TypeDescriptor, VarLenUnicode};
+use ndarray::ArrayD;
+use crate::experiment_manager::{ExperimentManager, OutcomeVariable};
 
 pub struct HDF5Manager {
     pub hdf5_file: File,
+    pub para_seed: Dataset,
     pub para_iteration: Dataset,
     pub para_time: Dataset,
     pub para_p_learning: Dataset,
     pub para_l_mech: Dataset,
+    /// Names of the social dynamics, in the order of the first axis of the results.
+    pub para_v_mech: Dataset,
+    pub para_homophily_s: Dataset,
+    pub para_homophily_p: Dataset,
+    /// Name of the learning rule.
+    pub para_learning_rule: Dataset,
+    pub para_p_socialization: Dataset,
+    pub para_p_code_learning: Dataset,
+    /// Names of the task environment and the NK interaction.
+    pub para_task_environment: Dataset,
+    pub para_nk_k: Dataset,
+    pub para_nk_interaction: Dataset,
     pub para_n: Dataset,
     pub para_m: Dataset,
     pub para_informal_init_p: Dataset,
@@ -26,233 +42,75 @@ pub struct HDF5Manager {
     pub para_l_turb: Dataset,
     pub para_v_turb_r: Dataset,
     pub para_v_turb_i: Dataset,
-    pub r_perf_avg: Dataset,
-    pub r_perf_std: Dataset,
-    pub r_perf_rr_avg: Dataset,
-    pub r_perf_rr_std: Dataset,
-    pub r_perf_nr_avg: Dataset,
-    pub r_perf_nr_std: Dataset,
-    pub r_perf_12_avg: Dataset,
-    pub r_perf_12_std: Dataset,
-    pub r_perf_23_avg: Dataset,
-    pub r_perf_23_std: Dataset,
-    pub r_perf_13_avg: Dataset,
-    pub r_perf_13_std: Dataset,
-    pub r_clws_avg: Dataset,
-    pub r_clws_std: Dataset,
-    pub r_clws_rr_avg: Dataset,
-    pub r_clws_rr_std: Dataset,
-    pub r_clws_nr_avg: Dataset,
-    pub r_clws_nr_std: Dataset,
-    pub r_clws_12_avg: Dataset,
-    pub r_clws_12_std: Dataset,
-    pub r_clws_23_avg: Dataset,
-    pub r_clws_23_std: Dataset,
-    pub r_clws_13_avg: Dataset,
-    pub r_clws_13_std: Dataset,
-    pub r_cent_avg: Dataset,
-    pub r_cent_std: Dataset,
-    pub r_cent_rr_avg: Dataset,
-    pub r_cent_rr_std: Dataset,
-    pub r_cent_nr_avg: Dataset,
-    pub r_cent_nr_std: Dataset,
-    pub r_cent_12_avg: Dataset,
-    pub r_cent_12_std: Dataset,
-    pub r_cent_23_avg: Dataset,
-    pub r_cent_23_std: Dataset,
-    pub r_cent_13_avg: Dataset,
-    pub r_cent_13_std: Dataset,
-    pub r_tria_avg: Dataset,
-    pub r_tria_std: Dataset,
-    pub r_tria_rr_avg: Dataset,
-    pub r_tria_rr_std: Dataset,
-    pub r_tria_nr_avg: Dataset,
-    pub r_tria_nr_std: Dataset,
-    pub r_tria_12_avg: Dataset,
-    pub r_tria_12_std: Dataset,
-    pub r_tria_23_avg: Dataset,
-    pub r_tria_23_std: Dataset,
-    pub r_tria_13_avg: Dataset,
-    pub r_tria_13_std: Dataset,
-    pub r_effi_avg: Dataset,
-    pub r_effi_std: Dataset,
-    pub r_effi_rr_avg: Dataset,
-    pub r_effi_rr_std: Dataset,
-    pub r_effi_nr_avg: Dataset,
-    pub r_effi_nr_std: Dataset,
-    pub r_effi_12_avg: Dataset,
-    pub r_effi_12_std: Dataset,
-    pub r_effi_23_avg: Dataset,
-    pub r_effi_23_std: Dataset,
-    pub r_effi_13_avg: Dataset,
-    pub r_effi_13_std: Dataset,
-    pub r_sigm_avg: Dataset,
-    pub r_sigm_std: Dataset,
-    pub r_sigm_rr_avg: Dataset,
-    pub r_sigm_rr_std: Dataset,
-    pub r_sigm_nr_avg: Dataset,
-    pub r_sigm_nr_std: Dataset,
-    pub r_sigm_12_avg: Dataset,
-    pub r_sigm_12_std: Dataset,
-    pub r_sigm_23_avg: Dataset,
-    pub r_sigm_23_std: Dataset,
-    pub r_sigm_13_avg: Dataset,
-    pub r_sigm_13_std: Dataset,
-    pub r_omeg_avg: Dataset,
-    pub r_omeg_std: Dataset,
-    pub r_omeg_rr_avg: Dataset,
-    pub r_omeg_rr_std: Dataset,
-    pub r_omeg_nr_avg: Dataset,
-    pub r_omeg_nr_std: Dataset,
-    pub r_omeg_12_avg: Dataset,
-    pub r_omeg_12_std: Dataset,
-    pub r_omeg_23_avg: Dataset,
-    pub r_omeg_23_std: Dataset,
-    pub r_omeg_13_avg: Dataset,
-    pub r_omeg_13_std: Dataset,
-    pub r_spva_avg: Dataset,
-    pub r_spva_std: Dataset,
-    pub r_spva_rr_avg: Dataset,
-    pub r_spva_rr_std: Dataset,
-    pub r_spva_nr_avg: Dataset,
-    pub r_spva_nr_std: Dataset,
-    pub r_spva_12_avg: Dataset,
-    pub r_spva_12_std: Dataset,
-    pub r_spva_23_avg: Dataset,
-    pub r_spva_23_std: Dataset,
-    pub r_spva_13_avg: Dataset,
-    pub r_spva_13_std: Dataset,
+    pub para_shard_i: Dataset,
+    pub para_shard_n: Dataset,
+    pub para_layered: Dataset,
+    /// `{name}_avg`, `_std`, `_se`, `_ci_lo`, `_ci_hi` and `_m2` of every outcome, in the order of `ExperimentManager::results`.
+    pub r_datasets: Vec<Dataset>,
+    /// Observations per cell, the same for every outcome (and every layer and bin).
+    pub r_count: Dataset,
     pub perf_seconds: Dataset,
     }
 
 impl HDF5Manager {
     pub fn new(experiment_manager:ExperimentManager, time_performance:u64) -> Self {
-        let hdf5_file = File::create(format!("{}{}.h5", *params::FILE_PATH, *params::FILE_NAME).as_str()).unwrap();
-        let para_iteration = hdf5_file.new_dataset_builder().with_data(&[params::ITERATION]).create("para_iteration").unwrap();
-        let para_time = hdf5_file.new_dataset_builder().with_data(&[params::TIME]).create("para_time").unwrap();
-        let para_p_learning = hdf5_file.new_dataset_builder().with_data(&[params::P_LEARNING]).create("para_p_learning").unwrap();
-        let para_n = hdf5_file.new_dataset_builder().with_data(&[params::N]).create("para_n").unwrap();
-        let para_m = hdf5_file.new_dataset_builder().with_data(&[params::M]).create("para_m").unwrap();
-        let para_informal_init_p = hdf5_file.new_dataset_builder().with_data(&[params::INFORMAL_INITIAL_PROP]).create("para_informal_init_p").unwrap();
-        let para_informal_init_n = hdf5_file.new_dataset_builder().with_data(&[params::INFORMAL_INITIAL_NUM]).create("para_informal_init_n").unwrap();
-        let para_informal_rewi_p = hdf5_file.new_dataset_builder().with_data(&[params::INFORMAL_REWIRING_PROP]).create("para_informal_rewi_p").unwrap();
-        let para_informal_rewi_n = hdf5_file.new_dataset_builder().with_data(&[params::INFORMAL_REWIRING_NUM]).create("para_informal_rewi_n").unwrap();
-        let para_informal_max = hdf5_file.new_dataset_builder().with_data(&[params::INFORMAL_MAX_NUM]).create("para_informal_max").unwrap();
-        let para_m_of_bundle = hdf5_file.new_dataset_builder().with_data(&[params::M_OF_BUNDLE]).create("para_m_of_bundle").unwrap();
-        let para_m_in_bundle = hdf5_file.new_dataset_builder().with_data(&[params::M_IN_BUNDLE]).create("para_m_in_bundle").unwrap();
-        let para_l_mech = hdf5_file.new_dataset_builder().with_data(&[params::NUM_SOCIAL_DYNAMICS]).create("para_l_mech").unwrap();
-        let para_l_span = hdf5_file.new_dataset_builder().with_data(&[params::LENGTH_SPAN]).create("para_l_span").unwrap();
-        let para_v_span = hdf5_file.new_dataset_builder().with_data(&params::SPAN).create("para_v_span").unwrap();
-        let para_l_enfo = hdf5_file.new_dataset_builder().with_data(&[params::LENGTH_ENFORCEMENT]).create("para_l_enfo").unwrap();
-        let para_v_enfo = hdf5_file.new_dataset_builder().with_data(&params::ENFORCEMENT).create("para_v_enfo").unwrap();
-        let para_l_turn = hdf5_file.new_dataset_builder().with_data(&[params::LENGTH_TURNOVER]).create("para_l_turn").unwrap();
-        let para_v_turn = hdf5_file.new_dataset_builder().with_data(&params::TURNOVER_RATE).create("para_v_turn").unwrap();
-        let para_l_turb = hdf5_file.new_dataset_builder().with_data(&[params::LENGTH_TURBULENCE]).create("para_l_turb").unwrap();
-        let para_v_turb_r = hdf5_file.new_dataset_builder().with_data(&params::TURBULENCE_RATE).create("para_v_turb_r").unwrap();
-        let para_v_turb_i = hdf5_file.new_dataset_builder().with_data(&params::TURBULENCE_INTERVAL).create("para_v_turb_i").unwrap();
-        let r_perf_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_avg.lock().unwrap().view()).create("r_perf_avg").unwrap();
-        let r_perf_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_std.lock().unwrap().view()).create("r_perf_std").unwrap();
-        let r_perf_nr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_nr_avg.lock().unwrap().view()).create("r_perf_nr_avg").unwrap();
-        let r_perf_nr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_nr_std.lock().unwrap().view()).create("r_perf_nr_std").unwrap();
-        let r_perf_rr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_rr_avg.lock().unwrap().view()).create("r_perf_rr_avg").unwrap();
-        let r_perf_rr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_rr_std.lock().unwrap().view()).create("r_perf_rr_std").unwrap();
-        let r_perf_12_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_12_avg.lock().unwrap().view()).create("r_perf_12_avg").unwrap();
-        let r_perf_12_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_12_std.lock().unwrap().view()).create("r_perf_12_std").unwrap();
-        let r_perf_23_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_23_avg.lock().unwrap().view()).create("r_perf_23_avg").unwrap();
-        let r_perf_23_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_23_std.lock().unwrap().view()).create("r_perf_23_std").unwrap();
-        let r_perf_13_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_13_avg.lock().unwrap().view()).create("r_perf_13_avg").unwrap();
-        let r_perf_13_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_perf_13_std.lock().unwrap().view()).create("r_perf_13_std").unwrap();
-        let r_clws_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_avg.lock().unwrap().view()).create("r_clws_avg").unwrap();
-        let r_clws_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_std.lock().unwrap().view()).create("r_clws_std").unwrap();
-        let r_clws_rr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_rr_avg.lock().unwrap().view()).create("r_clws_rr_avg").unwrap();
-        let r_clws_rr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_rr_std.lock().unwrap().view()).create("r_clws_rr_std").unwrap();
-        let r_clws_nr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_nr_avg.lock().unwrap().view()).create("r_clws_nr_avg").unwrap();
-        let r_clws_nr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_nr_std.lock().unwrap().view()).create("r_clws_nr_std").unwrap();
-        let r_clws_12_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_12_avg.lock().unwrap().view()).create("r_clws_12_avg").unwrap();
-        let r_clws_12_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_12_std.lock().unwrap().view()).create("r_clws_12_std").unwrap();
-        let r_clws_23_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_23_avg.lock().unwrap().view()).create("r_clws_23_avg").unwrap();
-        let r_clws_23_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_23_std.lock().unwrap().view()).create("r_clws_23_std").unwrap();
-        let r_clws_13_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_13_avg.lock().unwrap().view()).create("r_clws_13_avg").unwrap();
-        let r_clws_13_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_clws_13_std.lock().unwrap().view()).create("r_clws_13_std").unwrap();
-        let r_cent_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_avg.lock().unwrap().view()).create("r_cent_avg").unwrap();
-        let r_cent_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_std.lock().unwrap().view()).create("r_cent_std").unwrap();
-        let r_cent_rr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_rr_avg.lock().unwrap().view()).create("r_cent_rr_avg").unwrap();
-        let r_cent_rr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_rr_std.lock().unwrap().view()).create("r_cent_rr_std").unwrap();
-        let r_cent_nr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_nr_avg.lock().unwrap().view()).create("r_cent_nr_avg").unwrap();
-        let r_cent_nr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_nr_std.lock().unwrap().view()).create("r_cent_nr_std").unwrap();
-        let r_cent_12_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_12_avg.lock().unwrap().view()).create("r_cent_12_avg").unwrap();
-        let r_cent_12_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_12_std.lock().unwrap().view()).create("r_cent_12_std").unwrap();
-        let r_cent_23_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_23_avg.lock().unwrap().view()).create("r_cent_23_avg").unwrap();
-        let r_cent_23_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_23_std.lock().unwrap().view()).create("r_cent_23_std").unwrap();
-        let r_cent_13_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_13_avg.lock().unwrap().view()).create("r_cent_13_avg").unwrap();
-        let r_cent_13_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_cent_13_std.lock().unwrap().view()).create("r_cent_13_std").unwrap();
-        let r_tria_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_avg.lock().unwrap().view()).create("r_tria_avg").unwrap();
-        let r_tria_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_std.lock().unwrap().view()).create("r_tria_std").unwrap();
-        let r_tria_rr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_rr_avg.lock().unwrap().view()).create("r_tria_rr_avg").unwrap();
-        let r_tria_rr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_rr_std.lock().unwrap().view()).create("r_tria_rr_std").unwrap();
-        let r_tria_nr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_nr_avg.lock().unwrap().view()).create("r_tria_nr_avg").unwrap();
-        let r_tria_nr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_nr_std.lock().unwrap().view()).create("r_tria_nr_std").unwrap();
-        let r_tria_12_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_12_avg.lock().unwrap().view()).create("r_tria_12_avg").unwrap();
-        let r_tria_12_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_12_std.lock().unwrap().view()).create("r_tria_12_std").unwrap();
-        let r_tria_23_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_23_avg.lock().unwrap().view()).create("r_tria_23_avg").unwrap();
-        let r_tria_23_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_23_std.lock().unwrap().view()).create("r_tria_23_std").unwrap();
-        let r_tria_13_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_13_avg.lock().unwrap().view()).create("r_tria_13_avg").unwrap();
-        let r_tria_13_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_tria_13_std.lock().unwrap().view()).create("r_tria_13_std").unwrap();
-        let r_spva_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_avg.lock().unwrap().view()).create("r_spva_avg").unwrap();
-        let r_spva_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_std.lock().unwrap().view()).create("r_spva_std").unwrap();
-        let r_spva_rr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_rr_avg.lock().unwrap().view()).create("r_spva_rr_avg").unwrap();
-        let r_spva_rr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_rr_std.lock().unwrap().view()).create("r_spva_rr_std").unwrap();
-        let r_spva_nr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_nr_avg.lock().unwrap().view()).create("r_spva_nr_avg").unwrap();
-        let r_spva_nr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_nr_std.lock().unwrap().view()).create("r_spva_nr_std").unwrap();
-        let r_spva_12_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_12_avg.lock().unwrap().view()).create("r_spva_12_avg").unwrap();
-        let r_spva_12_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_12_std.lock().unwrap().view()).create("r_spva_12_std").unwrap();
-        let r_spva_23_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_23_avg.lock().unwrap().view()).create("r_spva_23_avg").unwrap();
-        let r_spva_23_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_23_std.lock().unwrap().view()).create("r_spva_23_std").unwrap();
-        let r_spva_13_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_13_avg.lock().unwrap().view()).create("r_spva_13_avg").unwrap();
-        let r_spva_13_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_spva_13_std.lock().unwrap().view()).create("r_spva_13_std").unwrap();
-        let r_effi_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_avg.lock().unwrap().view()).create("r_effi_avg").unwrap();
-        let r_effi_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_std.lock().unwrap().view()).create("r_effi_std").unwrap();
-        let r_effi_rr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_rr_avg.lock().unwrap().view()).create("r_effi_rr_avg").unwrap();
-        let r_effi_rr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_rr_std.lock().unwrap().view()).create("r_effi_rr_std").unwrap();
-        let r_effi_nr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_nr_avg.lock().unwrap().view()).create("r_effi_nr_avg").unwrap();
-        let r_effi_nr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_nr_std.lock().unwrap().view()).create("r_effi_nr_std").unwrap();
-        let r_effi_12_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_12_avg.lock().unwrap().view()).create("r_effi_12_avg").unwrap();
-        let r_effi_12_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_12_std.lock().unwrap().view()).create("r_effi_12_std").unwrap();
-        let r_effi_23_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_23_avg.lock().unwrap().view()).create("r_effi_23_avg").unwrap();
-        let r_effi_23_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_23_std.lock().unwrap().view()).create("r_effi_23_std").unwrap();
-        let r_effi_13_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_13_avg.lock().unwrap().view()).create("r_effi_13_avg").unwrap();
-        let r_effi_13_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_effi_13_std.lock().unwrap().view()).create("r_effi_13_std").unwrap();
-        let r_sigm_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_avg.lock().unwrap().view()).create("r_sigm_avg").unwrap();
-        let r_sigm_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_std.lock().unwrap().view()).create("r_sigm_std").unwrap();
-        let r_sigm_rr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_rr_avg.lock().unwrap().view()).create("r_sigm_rr_avg").unwrap();
-        let r_sigm_rr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_rr_std.lock().unwrap().view()).create("r_sigm_rr_std").unwrap();
-        let r_sigm_nr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_nr_avg.lock().unwrap().view()).create("r_sigm_nr_avg").unwrap();
-        let r_sigm_nr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_nr_std.lock().unwrap().view()).create("r_sigm_nr_std").unwrap();
-        let r_sigm_12_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_12_avg.lock().unwrap().view()).create("r_sigm_12_avg").unwrap();
-        let r_sigm_12_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_12_std.lock().unwrap().view()).create("r_sigm_12_std").unwrap();
-        let r_sigm_23_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_23_avg.lock().unwrap().view()).create("r_sigm_23_avg").unwrap();
-        let r_sigm_23_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_23_std.lock().unwrap().view()).create("r_sigm_23_std").unwrap();
-        let r_sigm_13_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_13_avg.lock().unwrap().view()).create("r_sigm_13_avg").unwrap();
-        let r_sigm_13_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_sigm_13_std.lock().unwrap().view()).create("r_sigm_13_std").unwrap();
-        let r_omeg_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_avg.lock().unwrap().view()).create("r_omeg_avg").unwrap();
-        let r_omeg_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_std.lock().unwrap().view()).create("r_omeg_std").unwrap();
-        let r_omeg_rr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_rr_avg.lock().unwrap().view()).create("r_omeg_rr_avg").unwrap();
-        let r_omeg_rr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_rr_std.lock().unwrap().view()).create("r_omeg_rr_std").unwrap();
-        let r_omeg_nr_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_nr_avg.lock().unwrap().view()).create("r_omeg_nr_avg").unwrap();
-        let r_omeg_nr_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_nr_std.lock().unwrap().view()).create("r_omeg_nr_std").unwrap();
-        let r_omeg_12_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_12_avg.lock().unwrap().view()).create("r_omeg_12_avg").unwrap();
-        let r_omeg_12_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_12_std.lock().unwrap().view()).create("r_omeg_12_std").unwrap();
-        let r_omeg_23_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_23_avg.lock().unwrap().view()).create("r_omeg_23_avg").unwrap();
-        let r_omeg_23_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_23_std.lock().unwrap().view()).create("r_omeg_23_std").unwrap();
-        let r_omeg_13_avg = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_13_avg.lock().unwrap().view()).create("r_omeg_13_avg").unwrap();
-        let r_omeg_13_std = hdf5_file.new_dataset_builder().with_data(&experiment_manager.r_omeg_13_std.lock().unwrap().view()).create("r_omeg_13_std").unwrap();
+        let config = &experiment_manager.config;
+        let hdf5_file = File::create(config.result_file_path()).unwrap();
+        let para_seed = hdf5_file.new_dataset_builder().with_data(&[config.seed]).create("para_seed").unwrap();
+        let para_iteration = hdf5_file.new_dataset_builder().with_data(&[config.iteration]).create("para_iteration").unwrap();
+        let para_time = hdf5_file.new_dataset_builder().with_data(&[config.time]).create("para_time").unwrap();
+        let para_p_learning = hdf5_file.new_dataset_builder().with_data(&[config.p_learning]).create("para_p_learning").unwrap();
+        let para_n = hdf5_file.new_dataset_builder().with_data(&[config.n]).create("para_n").unwrap();
+        let para_m = hdf5_file.new_dataset_builder().with_data(&[config.m]).create("para_m").unwrap();
+        let para_informal_init_p = hdf5_file.new_dataset_builder().with_data(&[config.informal_initial_prop]).create("para_informal_init_p").unwrap();
+        let para_informal_init_n = hdf5_file.new_dataset_builder().with_data(&[config.informal_initial_num]).create("para_informal_init_n").unwrap();
+        let para_informal_rewi_p = hdf5_file.new_dataset_builder().with_data(&[config.informal_rewiring_prop]).create("para_informal_rewi_p").unwrap();
+        let para_informal_rewi_n = hdf5_file.new_dataset_builder().with_data(&[config.informal_rewiring_num]).create("para_informal_rewi_n").unwrap();
+        let para_informal_max = hdf5_file.new_dataset_builder().with_data(&[config.informal_max_num]).create("para_informal_max").unwrap();
+        let para_m_of_bundle = hdf5_file.new_dataset_builder().with_data(&[config.m_of_bundle]).create("para_m_of_bundle").unwrap();
+        let para_m_in_bundle = hdf5_file.new_dataset_builder().with_data(&[config.m_in_bundle]).create("para_m_in_bundle").unwrap();
+        let para_l_mech = hdf5_file.new_dataset_builder().with_data(&[config.num_social_dynamics]).create("para_l_mech").unwrap();
+        let mech_names: Vec<VarLenUnicode> = config.social_dynamics.iter().map(|name| name.parse().unwrap()).collect();
+        let para_v_mech = hdf5_file.new_dataset_builder().with_data(mech_names.as_slice()).create("para_v_mech").unwrap();
+        let para_homophily_s = hdf5_file.new_dataset_builder().with_data(&[config.homophily_strength]).create("para_homophily_s").unwrap();
+        let para_homophily_p = hdf5_file.new_dataset_builder().with_data(&[config.homophily_performance_weight]).create("para_homophily_p").unwrap();
+        let para_learning_rule = hdf5_file.new_dataset_builder().with_data(&[config.learning_rule.parse::<VarLenUnicode>().unwrap()]).create("para_learning_rule").unwrap();
+        let para_p_socialization = hdf5_file.new_dataset_builder().with_data(&[config.p_socialization]).create("para_p_socialization").unwrap();
+        let para_p_code_learning = hdf5_file.new_dataset_builder().with_data(&[config.p_code_learning]).create("para_p_code_learning").unwrap();
+        let para_task_environment = hdf5_file.new_dataset_builder().with_data(&[config.task_environment.parse::<VarLenUnicode>().unwrap()]).create("para_task_environment").unwrap();
+        let para_nk_k = hdf5_file.new_dataset_builder().with_data(&[config.nk_k]).create("para_nk_k").unwrap();
+        let para_nk_interaction = hdf5_file.new_dataset_builder().with_data(&[config.nk_interaction.parse::<VarLenUnicode>().unwrap()]).create("para_nk_interaction").unwrap();
+        let para_l_span = hdf5_file.new_dataset_builder().with_data(&[config.length_span]).create("para_l_span").unwrap();
+        let para_v_span = hdf5_file.new_dataset_builder().with_data(config.span.as_slice()).create("para_v_span").unwrap();
+        let para_l_enfo = hdf5_file.new_dataset_builder().with_data(&[config.length_enforcement]).create("para_l_enfo").unwrap();
+        let para_v_enfo = hdf5_file.new_dataset_builder().with_data(config.enforcement.as_slice()).create("para_v_enfo").unwrap();
+        let para_l_turn = hdf5_file.new_dataset_builder().with_data(&[config.length_turnover]).create("para_l_turn").unwrap();
+        let para_v_turn = hdf5_file.new_dataset_builder().with_data(config.turnover_rate.as_slice()).create("para_v_turn").unwrap();
+        let para_l_turb = hdf5_file.new_dataset_builder().with_data(&[config.length_turbulence]).create("para_l_turb").unwrap();
+        let para_v_turb_r = hdf5_file.new_dataset_builder().with_data(config.turbulence_rate.as_slice()).create("para_v_turb_r").unwrap();
+        let para_v_turb_i = hdf5_file.new_dataset_builder().with_data(config.turbulence_interval.as_slice()).create("para_v_turb_i").unwrap();
+        let para_shard_i = hdf5_file.new_dataset_builder().with_data(&[config.shard.index]).create("para_shard_i").unwrap();
+        let para_shard_n = hdf5_file.new_dataset_builder().with_data(&[config.shard.count]).create("para_shard_n").unwrap();
+        let para_layered = hdf5_file.new_dataset_builder().with_data(&[config.layered_metrics as u8]).create("para_layered").unwrap();
+        let r_datasets = Self::write_outcomes(&hdf5_file, &experiment_manager.outcome_names, &experiment_manager.results).unwrap();
+        let r_count = hdf5_file.new_dataset_builder().with_data(&experiment_manager.results[0].count.view()).create("r_count").unwrap();
         let perf_seconds = hdf5_file.new_dataset_builder().with_data(&[time_performance]).create("perf_seconds").unwrap();
         HDF5Manager {
             hdf5_file,
+            para_seed,
             para_iteration,
             para_time,
             para_p_learning,
             para_l_mech,
+            para_v_mech,
+            para_homophily_s,
+            para_homophily_p,
+            para_learning_rule,
+            para_p_socialization,
+            para_p_code_learning,
+            para_task_environment,
+            para_nk_k,
+            para_nk_interaction,
             para_n,
             para_m,
             para_informal_init_p,
@@ -271,107 +129,163 @@ impl HDF5Manager {
             para_l_turb,
             para_v_turb_r,
             para_v_turb_i,
-            r_perf_avg,
-            r_perf_std,
-            r_perf_rr_avg,
-            r_perf_rr_std,
-            r_perf_nr_avg,
-            r_perf_nr_std,
-            r_perf_12_avg,
-            r_perf_12_std,
-            r_perf_23_avg,
-            r_perf_23_std,
-            r_perf_13_avg,
-            r_perf_13_std,
-            r_clws_avg,
-            r_clws_std,
-            r_clws_rr_avg,
-            r_clws_rr_std,
-            r_clws_nr_avg,
-            r_clws_nr_std,
-            r_clws_12_avg,
-            r_clws_12_std,
-            r_clws_23_avg,
-            r_clws_23_std,
-            r_clws_13_avg,
-            r_clws_13_std,
-            r_cent_avg,
-            r_cent_std,
-            r_cent_rr_avg,
-            r_cent_rr_std,
-            r_cent_nr_avg,
-            r_cent_nr_std,
-            r_cent_12_avg,
-            r_cent_12_std,
-            r_cent_23_avg,
-            r_cent_23_std,
-            r_cent_13_avg,
-            r_cent_13_std,   
-            r_tria_avg,
-            r_tria_std,
-            r_tria_rr_avg,
-            r_tria_rr_std,
-            r_tria_nr_avg,
-            r_tria_nr_std,
-            r_tria_12_avg,
-            r_tria_12_std,
-            r_tria_23_avg,
-            r_tria_23_std,
-            r_tria_13_avg,
-            r_tria_13_std,
-            r_spva_avg,
-            r_spva_std,
-            r_spva_rr_avg,
-            r_spva_rr_std,
-            r_spva_nr_avg,
-            r_spva_nr_std,
-            r_spva_12_avg,
-            r_spva_12_std,
-            r_spva_23_avg,
-            r_spva_23_std,
-            r_spva_13_avg,
-            r_spva_13_std,
-            r_effi_avg,
-            r_effi_std,
-            r_effi_rr_avg,
-            r_effi_rr_std,
-            r_effi_nr_avg,
-            r_effi_nr_std,
-            r_effi_12_avg,
-            r_effi_12_std,
-            r_effi_23_avg,
-            r_effi_23_std,
-            r_effi_13_avg,
-            r_effi_13_std,
-            r_sigm_avg,
-            r_sigm_std,
-            r_sigm_rr_avg,
-            r_sigm_rr_std,
-            r_sigm_nr_avg,
-            r_sigm_nr_std,
-            r_sigm_12_avg,
-            r_sigm_12_std,
-            r_sigm_23_avg,
-            r_sigm_23_std,
-            r_sigm_13_avg,
-            r_sigm_13_std,
-            r_omeg_avg,
-            r_omeg_std,
-            r_omeg_rr_avg,
-            r_omeg_rr_std,
-            r_omeg_nr_avg,
-            r_omeg_nr_std,
-            r_omeg_12_avg,
-            r_omeg_12_std,
-            r_omeg_23_avg,
-            r_omeg_23_std,
-            r_omeg_13_avg,
-            r_omeg_13_std,
+            para_shard_i,
+            para_shard_n,
+            para_layered,
+            r_datasets,
+            r_count,
             perf_seconds,
         }
     }
 
+    /// Writes the summary statistics of every outcome, and its `_m2` so that shards can be merged.
+    fn write_outcomes(hdf5_file: &File, outcome_names: &[String], results: &[OutcomeVariable]) -> hdf5::Result<Vec<Dataset>> {
+        let mut r_datasets = Vec::with_capacity(6 * results.len());
+        for (name, result) in outcome_names.iter().zip(results.iter()) {
+            let (ci_lo, ci_hi) = result.confidence_interval();
+            r_datasets.push(hdf5_file.new_dataset_builder().with_data(&result.mean.view()).create(format!("{}_avg", name).as_str())?);
+            r_datasets.push(hdf5_file.new_dataset_builder().with_data(&result.std_dev().view()).create(format!("{}_std", name).as_str())?);
+            r_datasets.push(hdf5_file.new_dataset_builder().with_data(&result.standard_error().view()).create(format!("{}_se", name).as_str())?);
+            r_datasets.push(hdf5_file.new_dataset_builder().with_data(&ci_lo.view()).create(format!("{}_ci_lo", name).as_str())?);
+            r_datasets.push(hdf5_file.new_dataset_builder().with_data(&ci_hi.view()).create(format!("{}_ci_hi", name).as_str())?);
+            r_datasets.push(hdf5_file.new_dataset_builder().with_data(&result.m2.view()).create(format!("{}_m2", name).as_str())?);
+        }
+        Ok(r_datasets)
+    }
+
     pub fn write_to_file(&self) {
         self.hdf5_file.flush().unwrap();
     }
-}
\ No newline at end of file
+
+    /// Combines the result files of every shard of one design into the result file of the whole run.
+    /// The shards must hold the same parameters (seed included) and cover each shard index exactly once.
+    pub fn merge<P: AsRef<Path>>(inputs: &[P], output: &Path) -> hdf5::Result<()> {
+        let files = inputs.iter().map(File::open).collect::<hdf5::Result<Vec<_>>>()?;
+        let display = |i: usize| inputs[i].as_ref().display().to_string();
+        let first = files.first().ok_or("no result file to merge")?;
+        let mut names = first.member_names()?;
+        names.sort();
+        let para_names: Vec<&String> = names.iter().filter(|name| name.starts_with("para_") && !name.starts_with("para_shard_")).collect();
+
+        let mut shards = Vec::with_capacity(files.len());
+        for (i, file) in files.iter().enumerate() {
+            let mut file_names = file.member_names()?;
+            file_names.sort();
+            if file_names != names {
+                return Err(format!("{} does not hold the same datasets as {}", display(i), display(0)).into());
+            }
+            for &name in para_names.iter() {
+                if ParaValue::read(&file.dataset(name)?)? != ParaValue::read(&first.dataset(name)?)? {
+                    return Err(format!("`{}` differs between {} and {}", name, display(i), display(0)).into());
+                }
+            }
+            shards.push((file.dataset("para_shard_i")?.read_raw::<u64>()?[0], file.dataset("para_shard_n")?.read_raw::<u64>()?[0]));
+        }
+        let count = shards[0].1;
+        let mut seen = vec![false; count as usize];
+        for (i, &(index, shard_count)) in shards.iter().enumerate() {
+            if shard_count != count || index >= count || seen[index as usize] {
+                return Err(format!("{} is shard {}/{}, which does not complete the other shards", display(i), index + 1, shard_count).into());
+            }
+            seen[index as usize] = true;
+        }
+        if let Some(missing) = seen.iter().position(|&seen| !seen) {
+            return Err(format!("shard {}/{} is missing", missing + 1, count).into());
+        }
+
+        let outcome_names: Vec<String> = names.iter().filter_map(|name| name.strip_suffix("_m2")).map(str::to_string).collect();
+        let mut results = Vec::with_capacity(outcome_names.len());
+        for name in outcome_names.iter() {
+            let mut merged: Option<OutcomeVariable> = None;
+            for file in files.iter() {
+                let mean = file.dataset(&format!("{}_avg", name))?.read_dyn::<f64>()?;
+                // Every iteration observes every cell, so the count is the same throughout,
+                // including along the layer and bin axes that `r_count` lacks.
+                let count = file.dataset("r_count")?.read_dyn::<u64>()?;
+                let iterations = *count.first().ok_or("`r_count` is empty")?;
+                if count.iter().any(|&cell| cell != iterations) {
+                    return Err("`r_count` differs between cells".into());
+                }
+                let part = OutcomeVariable {
+                    count: ArrayD::from_elem(mean.raw_dim(), iterations),
+                    mean,
+                    m2: file.dataset(&format!("{}_m2", name))?.read_dyn::<f64>()?,
+                };
+                match merged.as_mut() {
+                    Some(merged) => merged.merge(&part),
+                    None => merged = Some(part),
+                }
+            }
+            results.push(merged.unwrap());
+        }
+        let mut time_performance = 0;
+        for file in files.iter() {
+            time_performance += file.dataset("perf_seconds")?.read_raw::<u64>()?[0];
+        }
+
+        let hdf5_file = File::create(output)?;
+        for &name in para_names.iter() {
+            ParaValue::read(&first.dataset(name)?)?.write(&hdf5_file, name)?;
+        }
+        hdf5_file.new_dataset_builder().with_data(&[0_u64]).create("para_shard_i")?;
+        hdf5_file.new_dataset_builder().with_data(&[1_u64]).create("para_shard_n")?;
+        Self::write_outcomes(&hdf5_file, &outcome_names, &results)?;
+        hdf5_file.new_dataset_builder().with_data(&results[0].count.view()).create("r_count")?;
+        hdf5_file.new_dataset_builder().with_data(&[time_performance]).create("perf_seconds")?;
+        hdf5_file.flush()
+    }
+
+    /// Prints the stored `para_*` values and the shape of every other dataset in a result file.
+    pub fn inspect<P: AsRef<Path>>(path: P) -> hdf5::Result<()> {
+        let hdf5_file = File::open(path)?;
+        let mut names = hdf5_file.member_names()?;
+        names.sort();
+        for name in names {
+            let dataset = hdf5_file.dataset(&name)?;
+            if name.starts_with("para_") {
+                match ParaValue::read(&dataset)? {
+                    ParaValue::Unsigned(values) => println!("{}\t{:?}", name, values),
+                    ParaValue::Integer(values) => println!("{}\t{:?}", name, values),
+                    ParaValue::Float(values) => println!("{}\t{:?}", name, values),
+                    ParaValue::Text(values) => println!("{}\t{:?}", name, values),
+                }
+            } else {
+                println!("{}\tshape {:?}", name, dataset.shape());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The values of a `para_*` dataset in their stored type.
+#[derive(Debug, PartialEq)]
+enum ParaValue {
+    Unsigned(Vec<u64>),
+    Integer(Vec<i64>),
+    Float(Vec<f64>),
+    Text(Vec<String>),
+}
+
+impl ParaValue {
+    // Integers are read as such so that `para_seed` is kept exactly.
+    fn read(dataset: &Dataset) -> hdf5::Result<Self> {
+        Ok(match dataset.dtype()?.to_descriptor()? {
+            TypeDescriptor::Unsigned(_) => ParaValue::Unsigned(dataset.read_raw::<u64>()?),
+            TypeDescriptor::Integer(_) => ParaValue::Integer(dataset.read_raw::<i64>()?),
+            TypeDescriptor::VarLenUnicode => ParaValue::Text(dataset.read_raw::<VarLenUnicode>()?.iter().map(|value| value.as_str().to_string()).collect()),
+            _ => ParaValue::Float(dataset.read_raw::<f64>()?),
+        })
+    }
+
+    fn write(&self, hdf5_file: &File, name: &str) -> hdf5::Result<Dataset> {
+        match self {
+            ParaValue::Unsigned(values) => hdf5_file.new_dataset_builder().with_data(values.as_slice()).create(name),
+            ParaValue::Integer(values) => hdf5_file.new_dataset_builder().with_data(values.as_slice()).create(name),
+            ParaValue::Float(values) => hdf5_file.new_dataset_builder().with_data(values.as_slice()).create(name),
+            ParaValue::Text(values) => {
+                let values = values.iter().map(|value| value.parse::<VarLenUnicode>()).collect::<Result<Vec<_>, _>>().map_err(|error| error.to_string())?;
+                hdf5_file.new_dataset_builder().with_data(values.as_slice()).create(name)
+            }
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use rand::Rng;
+use crate::bit_vector::{BitCounter, BitVector};
+use crate::params::ExperimentConfig;
+use crate::scenario::Scenario;
+
+/// How agents revise their beliefs in a step of `Scenario::do_learning`.
+/// Every agent first works out which of its beliefs the rule contradicts, on the beliefs before the step;
+/// each of those then flips with probability `p_flip`.
+pub trait LearningRule: Sync {
+    /// Name under which the rule is chosen as `learning_rule` of the design.
+    fn name(&self) -> &'static str;
+
+    /// Short code, e.g. `SupMj`.
+    fn code(&self) -> &'static str;
+
+    /// The beliefs of `focal` that the rule would flip.
+    fn contradicted(&self, scenario: &Scenario, focal: usize) -> BitVector;
+
+    /// Probability that a contradicted belief flips.
+    fn p_flip(&self, config: &ExperimentConfig) -> f64 {
+        config.p_learning
+    }
+
+    /// Whether learning comes to rest once every agent performs the same, as it does when agents only
+    /// learn from better performers; `Scenario::step_forward` then skips it.
+    fn stops_when_even(&self) -> bool {
+        true
+    }
+
+    /// Whether the rule keeps an organizational code, `Scenario::organizational_code`.
+    fn uses_code(&self) -> bool {
+        false
+    }
+
+    /// Learning of the organization itself, run after every agent's contradicted beliefs are known
+    /// and before any of them flips, so that it sees the beliefs before the step as well.
+    fn learn_organization(&self, _scenario: &mut Scenario) {}
+}
+
+/// The beliefs of `focal` that the majority of `voters` contradicts; a tie contradicts nothing.
+/// The majorities are counted a word of dimensions at a time.
+fn contradicted_by_majority(scenario: &Scenario, focal: usize, voters: impl Iterator<Item = usize>) -> BitVector {
+    let mut count = BitCounter::new(scenario.config.m);
+    let mut num_voter = 0;
+    for voter in voters {
+        count.add(&scenario.belief_of[voter]);
+        num_voter += 1;
+    }
+    // A majority for 1 has more than half of the voters, one for 0 fewer than half.
+    let (above_half, at_half) = count.compare(num_voter / 2);
+    let is_tied = num_voter % 2 == 0;
+    let words: Vec<u64> = above_half.words().iter().zip(at_half.words()).zip(scenario.belief_of[focal].words())
+        .map(|((&above, &at), &belief)| {
+            let below = !above & !(if is_tied { at } else { 0 });
+            (above & !belief) | (below & belief)
+        })
+        .collect();
+    BitVector::from_words(scenario.config.m, words)
+}
+
+/// The beliefs in which `focal` differs from `model`.
+fn contradicted_by(scenario: &Scenario, focal: usize, model: &BitVector) -> BitVector {
+    let words = scenario.belief_of[focal].words().iter().zip(model.words()).map(|(belief, model)| belief ^ model).collect();
+    BitVector::from_words(scenario.config.m, words)
+}
+
+/// Agents move toward the majority opinion of their better-performing neighbors.
+pub struct SuperiorMajority;
+
+impl LearningRule for SuperiorMajority {
+    fn name(&self) -> &'static str {
+        "superior_majority"
+    }
+
+    fn code(&self) -> &'static str {
+        "SupMj"
+    }
+
+    fn contradicted(&self, scenario: &Scenario, focal: usize) -> BitVector {
+        let superiors = scenario.network.neighbors(focal).filter(|&neighbor| scenario.performance_of[neighbor] > scenario.performance_of[focal]);
+        contradicted_by_majority(scenario, focal, superiors)
+    }
+}
+
+/// Agents copy their best-performing neighbor, if it performs better than they do;
+/// among equally good neighbors, the one of the lowest index.
+pub struct BestNeighbor;
+
+impl LearningRule for BestNeighbor {
+    fn name(&self) -> &'static str {
+        "best_neighbor"
+    }
+
+    fn code(&self) -> &'static str {
+        "BstNb"
+    }
+
+    fn contradicted(&self, scenario: &Scenario, focal: usize) -> BitVector {
+        let best = scenario.network.neighbors(focal).fold(None, |best: Option<usize>, neighbor| match best {
+            Some(best) if scenario.performance_of[best] >= scenario.performance_of[neighbor] => Some(best),
+            _ => Some(neighbor),
+        });
+        match best {
+            Some(best) if scenario.performance_of[best] > scenario.performance_of[focal] => contradicted_by(scenario, focal, &scenario.belief_of[best]),
+            _ => BitVector::new(scenario.config.m),
+        }
+    }
+}
+
+/// Agents move toward the opinion of their neighbors weighted by performance;
+/// an even split contradicts nothing.
+pub struct PerformanceWeighted;
+
+impl LearningRule for PerformanceWeighted {
+    fn name(&self) -> &'static str {
+        "performance_weighted"
+    }
+
+    fn code(&self) -> &'static str {
+        "PrfWt"
+    }
+
+    fn contradicted(&self, scenario: &Scenario, focal: usize) -> BitVector {
+        let m = scenario.config.m;
+        let mut weight_one = vec![0.0; m];
+        let mut weight_total = 0.0;
+        for neighbor in scenario.network.neighbors(focal) {
+            let weight = scenario.performance_of[neighbor];
+            for dimension in scenario.belief_of[neighbor].ones() {
+                weight_one[dimension] += weight;
+            }
+            weight_total += weight;
+        }
+        let mut contradicted = BitVector::new(m);
+        for (dimension, &weight_one) in weight_one.iter().enumerate() {
+            let weight_zero = weight_total - weight_one;
+            let belief = scenario.belief_of[focal].get(dimension);
+            if (belief && weight_zero > weight_one) || (!belief && weight_one > weight_zero) {
+                contradicted.set(dimension, true);
+            }
+        }
+        contradicted
+    }
+
+    fn stops_when_even(&self) -> bool {
+        false
+    }
+}
+
+/// Agents move toward the majority opinion of all their neighbors, however they perform.
+pub struct Conformity;
+
+impl LearningRule for Conformity {
+    fn name(&self) -> &'static str {
+        "conformity"
+    }
+
+    fn code(&self) -> &'static str {
+        "Confm"
+    }
+
+    fn contradicted(&self, scenario: &Scenario, focal: usize) -> BitVector {
+        contradicted_by_majority(scenario, focal, scenario.network.neighbors(focal))
+    }
+
+    fn stops_when_even(&self) -> bool {
+        false
+    }
+}
+
+/// March's (1991) organizational code: agents are socialized into the code, each differing belief
+/// flipping with `p_socialization`, while the code learns from the agents who outperform it.
+/// Where the majority of those agents differs from the code by k votes, the code takes their view
+/// with probability 1 - (1 - `p_code_learning`)^k. The network plays no part in learning.
+pub struct OrganizationalCode;
+
+impl LearningRule for OrganizationalCode {
+    fn name(&self) -> &'static str {
+        "organizational_code"
+    }
+
+    fn code(&self) -> &'static str {
+        "OrgCd"
+    }
+
+    fn contradicted(&self, scenario: &Scenario, focal: usize) -> BitVector {
+        let code = scenario.organizational_code.as_ref().expect("the organizational code rule keeps a code");
+        contradicted_by(scenario, focal, code)
+    }
+
+    fn p_flip(&self, config: &ExperimentConfig) -> f64 {
+        config.p_socialization
+    }
+
+    fn stops_when_even(&self) -> bool {
+        false
+    }
+
+    fn uses_code(&self) -> bool {
+        true
+    }
+
+    fn learn_organization(&self, scenario: &mut Scenario) {
+        let Some(mut code) = scenario.organizational_code.take() else {
+            return;
+        };
+        let performance_code = scenario.get_performance_of_beliefs(&code);
+        let m = scenario.config.m;
+        let mut num_one = vec![0_usize; m];
+        let mut num_elite = 0;
+        for focal in (0..scenario.config.n).filter(|&focal| scenario.performance_of[focal] > performance_code) {
+            for dimension in scenario.belief_of[focal].ones() {
+                num_one[dimension] += 1;
+            }
+            num_elite += 1;
+        }
+        for (dimension, &num_one) in num_one.iter().enumerate() {
+            let num_zero = num_elite - num_one;
+            let majority = num_one > num_zero;
+            if num_one != num_zero && majority != code.get(dimension) {
+                let k = num_one.abs_diff(num_zero) as i32;
+                if scenario.rng.random::<f64>() < 1.0 - (1.0 - scenario.config.p_code_learning).powi(k) {
+                    code.flip(dimension);
+                }
+            }
+        }
+        scenario.organizational_code = Some(code);
+    }
+}
+
+/// Every rule that can be named in a design. Adding one only takes a new entry here;
+/// the HDF5 output records a rule by its position, so new rules go at the end.
+pub static LEARNING_RULES: &[&dyn LearningRule] = &[&SuperiorMajority, &BestNeighbor, &PerformanceWeighted, &Conformity, &OrganizationalCode];
+
+/// The registered rule called `name`.
+pub fn find(name: &str) -> Option<&'static dyn LearningRule> {
+    LEARNING_RULES.iter().copied().find(|rule| rule.name() == name)
+}
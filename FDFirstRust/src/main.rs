@@ -1,44 +1,107 @@
 pub mod params;
+pub mod learning_rule;
 pub mod scenario;
+pub mod social_dynamics;
+pub mod task_environment;
+pub mod adjacency;
+pub mod bit_vector;
 pub mod network_analyzer;
+pub mod weighted_sampler;
+pub mod community;
 pub mod experiment_manager;
 pub mod hdf5_manager;
+pub mod cli;
+pub mod checkpoint;
+pub mod metric;
 
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::process;
+use std::sync::Arc;
 use std::thread::available_parallelism;
+use std::time::Instant;
+use checkpoint::Checkpoint;
+use clap::Parser;
+use cli::{Cli, Command};
 use experiment_manager::ExperimentManager;
 use hdf5_manager::HDF5Manager;
+use params::ExperimentConfig;
 use rayon::ThreadPoolBuilder;
 
-fn main() -> std::io::Result<()> {    
-    params::initialize_once_cells();
-    println!("Simulation through {}", params::PARAM_STRING.clone());
+fn main() -> io::Result<()> {
+    let cli = Cli::parse();
 
-    let mut num_thread = params::MAX_THREAD;
-    if num_thread > available_parallelism().unwrap().get() {
-        num_thread = available_parallelism().unwrap().get()
+    match cli.command {
+        Command::Run { design, resume, shard } => {
+            let mut config = design.load_config()?;
+            if let Some(shard) = shard {
+                config.shard = shard;
+                config.set_second_order_params();
+            }
+            let checkpoint = if resume { Checkpoint::load(config.checkpoint_file_path())? } else { None };
+            match &checkpoint {
+                // The resumed iterations must draw from the streams of the interrupted run.
+                Some(checkpoint) => {
+                    config.seed = checkpoint.design.seed;
+                    println!("Resuming after {} of {} iterations", checkpoint.finished.len(), config.iteration);
+                }
+                None if resume => println!("No checkpoint at {}; starting afresh", config.checkpoint_file_path().display()),
+                None => {}
+            }
+            let config = prepare(config)?;
+            let mut experiment_manager = ExperimentManager::new(Arc::clone(&config));
+            if let Some(checkpoint) = checkpoint {
+                experiment_manager.restore(checkpoint)?;
+            }
+            let tic = Instant::now();
+            experiment_manager.run_experiments()?;
+            let hdf5_manager = HDF5Manager::new(experiment_manager, tic.elapsed().as_secs());
+            hdf5_manager.write_to_file();
+            if config.checkpoint_interval > 0 {
+                match fs::remove_file(config.checkpoint_file_path()) {
+                    Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                    _ => {}
+                }
+            }
+        }
+        Command::Sample { design, init } => {
+            let mut config = design.load_config()?;
+            config.get_graph_init |= init;
+            let config = prepare(config)?;
+            fs::create_dir_all(config.sample_dir_path())?;
+            let experiment_manager = ExperimentManager::new(Arc::clone(&config));
+            experiment_manager.sample_network_csv();
+        }
+        Command::Merge { files, output } => {
+            HDF5Manager::merge(&files, &output).map_err(io::Error::other)?;
+            println!("Merged {} shards into {}", files.len(), output.display());
+        }
+        Command::Inspect { file } => {
+            HDF5Manager::inspect(&file).map_err(io::Error::other)?;
+        }
     }
-    ThreadPoolBuilder::new().num_threads(num_thread).build_global().expect("Failed to build the global Rayon thread pool");
-    println!("This simulation will run on {} threads", num_thread);
 
-    let mut experiment_manager = ExperimentManager::new();
+    Ok(())
+}
 
-    if params::GET_GRAPH {
-        let folder_name = (*params::PARAM_STRING).clone();
-        if !Path::new(&folder_name).exists() {
-            fs::create_dir(&folder_name)?;
-        }
-        experiment_manager.sample_network_csv();
+/// Validates a design about to run, then sets up its output directory and the global thread pool.
+fn prepare(config: ExperimentConfig) -> io::Result<Arc<ExperimentConfig>> {
+    if let Err(errors) = config.check_validity() {
+        eprint!("{}", errors);
+        process::exit(1);
     }
+    println!("Simulation through {} (seed {})", config.param_string, config.seed);
+    if !config.shard.is_whole() {
+        println!("Running shard {} of the iterations", config.shard);
+    }
+    fs::create_dir_all(&config.output_dir)?;
 
-    if params::GET_MAT {
-        let tic = std::time::Instant::now().elapsed().as_secs();
-        experiment_manager.run_experiments();
-        let toc = std::time::Instant::now().elapsed().as_secs();
-        let hdf5_manager = HDF5Manager::new(experiment_manager, toc-tic);
-        hdf5_manager.write_to_file();
+    let mut num_thread = config.max_thread;
+    if num_thread > available_parallelism().unwrap().get() {
+        num_thread = available_parallelism().unwrap().get()
     }
+    ThreadPoolBuilder::new().num_threads(num_thread).build_global().expect("Failed to build the global Rayon thread pool");
+    println!("This simulation will run on {} threads", num_thread);
 
-    Ok(())
+    Ok(Arc::new(config))
 }
@@ -0,0 +1,145 @@
+use crate::network_analyzer::NetworkAnalyzer;
+use crate::params::{ExperimentConfig, ARM_NAMES, ARM_SC, NUM_ARMS, NUM_LAYERS};
+use crate::scenario::Scenario;
+
+/// Where the value of a metric is read.
+pub enum Source {
+    Scenario(fn(&Scenario) -> f64),
+    /// A network metric, read from the analyzer of each layer when `layered_metrics` is set.
+    Network(fn(&NetworkAnalyzer) -> f64),
+    /// A distribution over the N possible degrees, recorded along a bin axis after the time axis
+    /// when `degree_histograms` is set.
+    NetworkHistogram(fn(&NetworkAnalyzer) -> &[f64]),
+    /// A metric of the organizational code, recorded when the learning rule keeps one.
+    OrganizationalCode(fn(&Scenario) -> f64),
+}
+
+/// An outcome read off every arm at every time step.
+pub struct Metric {
+    /// Short code used in the dataset names, e.g. `perf` in `r_perf_rr_avg`.
+    pub name: &'static str,
+    pub source: Source,
+}
+
+impl Metric {
+    pub fn is_network(&self) -> bool {
+        matches!(self.source, Source::Network(_) | Source::NetworkHistogram(_))
+    }
+
+    pub fn is_recorded(&self, config: &ExperimentConfig) -> bool {
+        match self.source {
+            Source::NetworkHistogram(_) => config.degree_histograms,
+            Source::OrganizationalCode(_) => config.get_learning_rule().uses_code(),
+            _ => true,
+        }
+    }
+
+    pub fn num_bins(&self, config: &ExperimentConfig) -> usize {
+        if matches!(self.source, Source::NetworkHistogram(_)) { config.n } else { 1 }
+    }
+
+    /// Number of layers the metric is recorded for: all of them for network metrics under
+    /// `layered_metrics`, otherwise only the whole network.
+    pub fn num_layers(&self, config: &ExperimentConfig) -> usize {
+        if self.is_network() && config.layered_metrics { NUM_LAYERS } else { 1 }
+    }
+
+    /// Shape of the recorded arrays, with a layer axis before the time axis when there are several layers
+    /// and a bin axis after it for histograms.
+    pub fn shape(&self, config: &ExperimentConfig) -> Vec<usize> {
+        let mut shape = if self.num_layers(config) > 1 { config.result_shape_layered.clone() } else { config.result_shape.clone() };
+        if self.num_bins(config) > 1 {
+            shape.push(self.num_bins(config));
+        }
+        shape
+    }
+
+    /// The value in a scenario; `layer` only matters for network metrics and `bin` for histograms.
+    pub fn value(&self, scenario: &Scenario, layer: usize, bin: usize) -> f64 {
+        match self.source {
+            Source::Scenario(extract) | Source::OrganizationalCode(extract) => extract(scenario),
+            Source::Network(extract) => extract(scenario.analyzer_of(layer)),
+            Source::NetworkHistogram(extract) => extract(scenario.analyzer_of(layer))[bin],
+        }
+    }
+}
+
+/// Every registered metric is accumulated and written for each series in `series()`.
+/// Adding an outcome only takes a new entry here.
+pub static METRICS: &[Metric] = &[
+    Metric { name: "perf", source: Source::Scenario(|scenario| scenario.performance_avg) },
+    Metric { name: "pcod", source: Source::OrganizationalCode(|scenario| scenario.performance_code) },
+    Metric { name: "clws", source: Source::Network(NetworkAnalyzer::get_global_clustering_watts_strogatz) },
+    Metric { name: "cent", source: Source::Network(NetworkAnalyzer::get_closeness_centralization) },
+    Metric { name: "tria", source: Source::Network(NetworkAnalyzer::get_triadic_centralization) },
+    Metric { name: "betw", source: Source::Network(NetworkAnalyzer::get_betweenness_centralization) },
+    Metric { name: "dcen", source: Source::Network(NetworkAnalyzer::get_degree_centralization) },
+    Metric { name: "dass", source: Source::Network(NetworkAnalyzer::get_degree_assortativity) },
+    Metric { name: "lass", source: Source::Network(NetworkAnalyzer::get_level_assortativity) },
+    Metric { name: "dhis", source: Source::NetworkHistogram(NetworkAnalyzer::get_degree_histogram) },
+    Metric { name: "hcen", source: Source::Network(NetworkAnalyzer::get_harmonic_centralization) },
+    Metric { name: "aspl", source: Source::Network(NetworkAnalyzer::get_average_path_length) },
+    Metric { name: "ncmp", source: Source::Network(|analyzer| analyzer.get_num_component() as f64) },
+    Metric { name: "gcsh", source: Source::Network(NetworkAnalyzer::get_giant_component_share) },
+    Metric { name: "disc", source: Source::Network(|analyzer| analyzer.is_disconnected() as u8 as f64) },
+    Metric { name: "spva", source: Source::Network(NetworkAnalyzer::get_shortest_path_variance) },
+    Metric { name: "effi", source: Source::Network(NetworkAnalyzer::get_network_efficiency) },
+    Metric { name: "sigm", source: Source::Network(NetworkAnalyzer::get_sigma) },
+    Metric { name: "omeg", source: Source::Network(NetworkAnalyzer::get_omega) },
+    Metric { name: "ncom", source: Source::Network(|analyzer| analyzer.get_num_community() as f64) },
+    Metric { name: "modu", source: Source::Network(NetworkAnalyzer::get_modularity) },
+    Metric { name: "nmif", source: Source::Network(NetworkAnalyzer::get_nmi_formal) },
+    Metric { name: "iwun", source: Source::Scenario(|scenario| scenario.informal_within_unit) },
+    Metric { name: "mpar", source: Source::Scenario(|scenario| scenario.multiplex_participation) },
+];
+
+/// How a recorded series is derived from the values of a metric in each arm.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SeriesKind {
+    Arm(usize),
+    /// The first arm minus the second.
+    Contrast(usize, usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Series {
+    /// Suffix in the dataset names: the arm name (none for the systematic arm) or the contrast, e.g. `12`.
+    pub suffix: String,
+    pub kind: SeriesKind,
+}
+
+impl Series {
+    pub fn value(&self, by_arm: &[f64; NUM_ARMS]) -> f64 {
+        match self.kind {
+            SeriesKind::Arm(arm) => by_arm[arm],
+            SeriesKind::Contrast(first, second) => by_arm[first] - by_arm[second],
+        }
+    }
+}
+
+/// Each arm on its own, then every pairwise contrast between arms (`12`, `13`, `23`).
+pub fn series() -> Vec<Series> {
+    let mut series = Vec::new();
+    for (arm, name) in ARM_NAMES.iter().enumerate() {
+        let suffix = if arm == ARM_SC { String::new() } else { name.to_string() };
+        series.push(Series { suffix, kind: SeriesKind::Arm(arm) });
+    }
+    for first in 0..NUM_ARMS {
+        for second in (first + 1)..NUM_ARMS {
+            series.push(Series {
+                suffix: format!("{}{}", first + 1, second + 1),
+                kind: SeriesKind::Contrast(first, second),
+            });
+        }
+    }
+    series
+}
+
+/// Dataset base name of a metric in a series, e.g. `r_perf` or `r_perf_rr`.
+pub fn outcome_name(metric: &Metric, series: &Series) -> String {
+    if series.suffix.is_empty() {
+        format!("r_{}", metric.name)
+    } else {
+        format!("r_{}_{}", metric.name, series.suffix)
+    }
+}
@@ -1,20 +1,53 @@
-use crate::params;
+use crate::adjacency::AdjacencyMatrix;
+use crate::community;
+use crate::params::ExperimentConfig;
+use crate::scenario::ScenarioRng;
+use rand::Rng;
 use std::collections::VecDeque;
-use std::{f64, usize};
+use std::sync::Arc;
 
 pub struct NetworkAnalyzer {
-    pub shortest_path: Vec<Vec<isize>>, // declared as an signed integer to allow for -1 as a procedural marker.
+    config: Arc<ExperimentConfig>,
+
+    pub shortest_path: Vec<Vec<u32>>, // `UNREACHABLE` between agents without a path.
+    pub betweenness_centrality: Vec<f64>, // number of shortest paths between other pairs through each node, split among ties.
+    dependency: Vec<Vec<f64>>, // Brandes' dependency of each source on every node, kept to update the betweenness.
 
     adj_list: Vec<Vec<usize>>,
 
-    pub average_path_length: f64,
+    // The networks the current metrics were computed on; an unchanged network is not analyzed again.
+    network_analyzed: Option<AdjacencyMatrix>,
+    network_partitioned: Option<AdjacencyMatrix>,
+
+    // Draws the random reference graphs of sigma and omega.
+    rng: ScenarioRng,
+    // Clustering of the ring lattice of omega, for the number of ties it was last needed for.
+    clustering_lattice: Option<(usize, f64)>,
+
+    pub average_path_length: f64, // over the dyads connected by a path.
     pub network_efficiency: f64,
     pub global_clustering_watts_strogatz: f64,
     pub centralization_closeness: f64,
     pub centralization_triadic_participation: f64,
+    pub centralization_betweenness: f64,
+    pub centralization_harmonic: f64,
+
+    pub num_component: usize,
+    pub giant_component_share: f64,
+    pub is_disconnected: bool,
     pub shortest_path_variance: f64,
     pub sigma: f64,
     pub omega: f64,
+
+    pub degree_centralization: f64,
+    pub degree_assortativity: f64,
+    pub level_assortativity: f64,
+    pub degree_histogram: Vec<f64>, // share of the agents with each degree 0..N-1.
+
+    pub community_of: Vec<usize>,
+    pub num_community: usize,
+    pub modularity: f64,
+    pub nmi_formal: f64,
 }
 
 impl NetworkAnalyzer {
@@ -33,6 +66,8 @@ impl NetworkAnalyzer {
         self.global_clustering_watts_strogatz
     }
 
+    /// Closeness sums the distances to the reachable agents only, so it overstates how central the agents
+    /// of a disconnected network are; it is kept for comparison with earlier runs, next to `get_harmonic_centralization`.
     pub fn get_closeness_centralization(&self) -> f64 {
         self.centralization_closeness
     }
@@ -41,6 +76,27 @@ impl NetworkAnalyzer {
         self.centralization_triadic_participation
     }
 
+    pub fn get_betweenness_centralization(&self) -> f64 {
+        self.centralization_betweenness
+    }
+
+    pub fn get_harmonic_centralization(&self) -> f64 {
+        self.centralization_harmonic
+    }
+
+    pub fn get_num_component(&self) -> usize {
+        self.num_component
+    }
+
+    pub fn get_giant_component_share(&self) -> f64 {
+        self.giant_component_share
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.is_disconnected
+    }
+
+    /// Variance of the distances from each agent to the agents it reaches, averaged over the agents that reach any.
     pub fn get_shortest_path_variance(&self) -> f64 {
         self.shortest_path_variance
     }
@@ -53,19 +109,71 @@ impl NetworkAnalyzer {
         self.sigma
     }
 
-    /// Initializes empty fields.
-    pub fn new() -> Self {
+    pub fn get_degree_centralization(&self) -> f64 {
+        self.degree_centralization
+    }
+
+    pub fn get_degree_assortativity(&self) -> f64 {
+        self.degree_assortativity
+    }
+
+    pub fn get_level_assortativity(&self) -> f64 {
+        self.level_assortativity
+    }
+
+    pub fn get_degree_histogram(&self) -> &[f64] {
+        &self.degree_histogram
+    }
+
+    pub fn get_num_community(&self) -> usize {
+        self.num_community
+    }
+
+    pub fn get_modularity(&self) -> f64 {
+        self.modularity
+    }
+
+    pub fn get_nmi_formal(&self) -> f64 {
+        self.nmi_formal
+    }
+
+    /// Initializes empty fields for the analysis of one network layer.
+    /// The reference graphs are drawn from a far-off position of the scenario's stream, one per layer,
+    /// so sampling them leaves the trajectory of the scenario unchanged.
+    pub fn new(config: &Arc<ExperimentConfig>, scenario_rng: &ScenarioRng, layer: usize) -> Self {
+        let mut rng = scenario_rng.clone();
+        rng.set_word_pos((1 << 67) + ((layer as u128) << 64));
         NetworkAnalyzer {
-            shortest_path: vec![vec![-1; params::N]; params::N],
-            adj_list: vec![Vec::new(); params::N],
+            config: Arc::clone(config),
+            shortest_path: vec![vec![UNREACHABLE; config.n]; config.n],
+            betweenness_centrality: vec![0.0; config.n],
+            dependency: vec![vec![0.0; config.n]; config.n],
+            adj_list: vec![Vec::new(); config.n],
+            network_analyzed: None,
+            network_partitioned: None,
+            rng,
+            clustering_lattice: None,
             average_path_length: 0.0,
             network_efficiency: 0.0,
             global_clustering_watts_strogatz: 0.0,
             centralization_closeness: 0.0,
             centralization_triadic_participation: 0.0,
+            centralization_betweenness: 0.0,
+            centralization_harmonic: 0.0,
+            num_component: 0,
+            giant_component_share: 0.0,
+            is_disconnected: false,
             shortest_path_variance: 0.0,
             sigma: 0.0,
             omega: 0.0,
+            degree_centralization: 0.0,
+            degree_assortativity: 0.0,
+            level_assortativity: 0.0,
+            degree_histogram: vec![0.0; config.n],
+            community_of: vec![0; config.n],
+            num_community: 0,
+            modularity: 0.0,
+            nmi_formal: 0.0,
         }
     }
 
@@ -73,8 +181,14 @@ impl NetworkAnalyzer {
     /// 1) Build adjacency list
     /// 2) Compute shortest paths & betweenness
     /// 3) Compute average path length, network efficiency, closeness, clustering, etc.
-    pub fn set_network_metrics(&mut self, network2_analyze: &Vec<Vec<bool>>) {
-        self.set_shortest_path(&network2_analyze);
+    ///
+    /// Nothing is recomputed when the network is the one analyzed last.
+    pub fn set_network_metrics(&mut self, network2_analyze: &AdjacencyMatrix) {
+        if self.network_analyzed.as_ref() == Some(network2_analyze) {
+            return;
+        }
+        self.set_shortest_path(network2_analyze);
+        self.network_analyzed = Some(network2_analyze.clone());
 
         // In Java: double diameter = Double.MIN_VALUE;
         // `Double.MIN_VALUE` in Java is the smallest positive number (~1e-308).
@@ -89,27 +203,37 @@ impl NetworkAnalyzer {
         self.shortest_path_variance = 0.0;
         self.global_clustering_watts_strogatz = 0.0;
 
-        let mut centrality_closeness = vec![0.0; params::N];
+        let mut centrality_closeness = vec![0.0; self.config.n];
         let mut centrality_closeness_max = f64::MIN;
-        let mut centrality_triadic = vec![0.0; params::N];
+        let mut centrality_triadic = vec![0.0; self.config.n];
         let mut centrality_triadic_max = f64::MIN;
-        let mut shortest_path_sum = vec![0.0; params::N];
-        let mut shortest_path_squared_sum = vec![0.0; params::N];
+        let mut shortest_path_sum = vec![0.0; self.config.n];
+        let mut shortest_path_squared_sum = vec![0.0; self.config.n];
+        let mut num_reachable = vec![0usize; self.config.n];
+        // Harmonic closeness stays meaningful when the network falls apart: unreachable agents add 0.
+        let mut centrality_harmonic = vec![0.0; self.config.n];
+        let mut num_connected_dyad = 0;
 
         // Main loop to accumulate statistics
-        for i in 0..params::N {
+        for i in 0..self.config.n {
             let degree_i = self.adj_list[i].len();
-            for j in i..params::N {
+            for j in i..self.config.n {
                 if i != j {
                     let dist = self.shortest_path[i][j];
-                    if dist > 0 {
+                    if dist != UNREACHABLE {
                         let dist_f = dist as f64;
+                        num_connected_dyad += 1;
+                        centrality_harmonic[i] += 1.0 / dist_f;
+                        centrality_harmonic[j] += 1.0 / dist_f;
                         self.average_path_length += dist_f;
                         self.network_efficiency += 1.0 / dist_f;
                         centrality_closeness[i] += dist_f;
                         centrality_closeness[j] += dist_f;
-                        shortest_path_sum[i] += dist_f;
-                        shortest_path_squared_sum[i] += dist_f * dist_f;
+                        for end in [i, j] {
+                            shortest_path_sum[end] += dist_f;
+                            shortest_path_squared_sum[end] += dist_f * dist_f;
+                            num_reachable[end] += 1;
+                        }
 
                         if dist_f > diameter {
                             diameter = dist_f;
@@ -121,7 +245,7 @@ impl NetworkAnalyzer {
             // closenessCentrality[i] = (N - 1) / closenessCentrality[i]
             // and accumulate for global closeness centralization.
             if centrality_closeness[i] > 0.0 {
-                centrality_closeness[i] = (params::N as f64 - 1.0) / centrality_closeness[i];
+                centrality_closeness[i] = (self.config.n as f64 - 1.0) / centrality_closeness[i];
             }
             self.centralization_closeness -= centrality_closeness[i];
             if centrality_closeness[i] > centrality_closeness_max {
@@ -130,16 +254,13 @@ impl NetworkAnalyzer {
 
             // local clustering (Watts-Strogatz)
             if degree_i >= 2 {
-                let mut local_clustering_numerator = 0;
+                // Each tie among the neighbors is a common neighbor of i and either of its ends.
+                let local_clustering_numerator = self.adj_list[i]
+                    .iter()
+                    .map(|&j| network2_analyze.common_neighbors(i, j))
+                    .sum::<usize>()
+                    / 2;
                 let local_clustering_denominator = degree_i * (degree_i - 1) / 2;
-
-                for &j in &self.adj_list[i] {
-                    for &k in &self.adj_list[i] {
-                        if j < k && network2_analyze[j][k] {
-                            local_clustering_numerator += 1;
-                        }
-                    }
-                }
                 self.global_clustering_watts_strogatz +=
                     local_clustering_numerator as f64 / local_clustering_denominator as f64;
                 centrality_triadic[i] = local_clustering_numerator as f64;
@@ -150,80 +271,602 @@ impl NetworkAnalyzer {
             }
         }
 
-        // Compute the variance of shortest paths for each node
-        for i in 0..params::N {
-            let mean = shortest_path_sum[i] / params::N as f64;
-            let mean_square = shortest_path_squared_sum[i] / params::N as f64;
+        // Compute the variance of shortest paths for each node, over the agents it reaches
+        let mut num_reaching = 0;
+        for i in (0..self.config.n).filter(|&i| num_reachable[i] > 0) {
+            let mean = shortest_path_sum[i] / num_reachable[i] as f64;
+            let mean_square = shortest_path_squared_sum[i] / num_reachable[i] as f64;
             self.shortest_path_variance += mean_square - (mean * mean);
+            num_reaching += 1;
         }
 
         // Final normalization
-        self.average_path_length /= params::N_DYAD as f64;
-        self.network_efficiency /= params::N_DYAD as f64;
-        self.centralization_closeness += centrality_closeness_max * (params::N as f64);
-        self.centralization_closeness /= params::CLOSENESS_CENTRALIZATION_DENOMINATOR;
-        self.centralization_triadic_participation += centrality_triadic_max * (params::N as f64);
-        self.centralization_triadic_participation /= params::TRIADIC_CENTRALIZATION_DENOMINATOR;
-        self.global_clustering_watts_strogatz /= params::N as f64;
-        self.shortest_path_variance /= params::N as f64;
-        self.sigma = (self.global_clustering_watts_strogatz / params::CLUSTERING_COEFFICIENT_RANDOM) / (self.average_path_length / *params::AVERAGE_PATH_LENGTH_RANDOM);
-        self.omega = (self.average_path_length / *params::AVERAGE_PATH_LENGTH_RANDOM) - (params::CLUSTERING_COEFFICIENT_RANDOM / self.global_clustering_watts_strogatz);
-    
+        self.average_path_length = if num_connected_dyad == 0 { 0.0 } else { self.average_path_length / num_connected_dyad as f64 };
+        self.network_efficiency /= self.config.n_dyad as f64;
+        self.centralization_closeness += centrality_closeness_max * (self.config.n as f64);
+        self.centralization_closeness /= self.config.closeness_centralization_denominator;
+        self.centralization_triadic_participation += centrality_triadic_max * (self.config.n as f64);
+        self.centralization_triadic_participation /= self.config.triadic_centralization_denominator;
+        self.global_clustering_watts_strogatz /= self.config.n as f64;
+        self.shortest_path_variance = if num_reaching == 0 { 0.0 } else { self.shortest_path_variance / num_reaching as f64 };
+
+        // Harmonic closeness (Σ_j 1 / d_ij) / (N - 1); relative to the star, whose shortfall sums to (N - 2) / 2.
+        let centrality_harmonic_max = centrality_harmonic.iter().cloned().fold(0.0, f64::max);
+        let harmonic_denominator = (self.config.n as f64 - 1.0) * (self.config.n as f64 - 2.0) / 2.0;
+        self.centralization_harmonic = if harmonic_denominator > 0.0 {
+            centrality_harmonic.iter().map(|centrality| centrality_harmonic_max - centrality).sum::<f64>() / harmonic_denominator
+        } else {
+            0.0
+        };
+
+        // Connected components, from the reachability found by `set_shortest_path`.
+        let mut component_of = vec![usize::MAX; self.config.n];
+        let mut giant_component_size = 0;
+        self.num_component = 0;
+        for i in 0..self.config.n {
+            if component_of[i] == usize::MAX {
+                let mut size = 0;
+                for (component, &distance) in component_of.iter_mut().zip(self.shortest_path[i].iter()) {
+                    if distance != UNREACHABLE {
+                        *component = self.num_component;
+                        size += 1;
+                    }
+                }
+                giant_component_size = giant_component_size.max(size);
+                self.num_component += 1;
+            }
+        }
+        self.giant_component_share = giant_component_size as f64 / self.config.n as f64;
+        self.is_disconnected = self.num_component > 1;
+
+        // Freeman centralization: the summed shortfall from the most central node, relative to the star.
+        let centrality_betweenness_max = self.betweenness_centrality.iter().cloned().fold(0.0, f64::max);
+        self.centralization_betweenness = self.betweenness_centrality
+            .iter()
+            .map(|centrality| centrality_betweenness_max - centrality)
+            .sum::<f64>()
+            / self.config.betweenness_centralization_denominator;
+
+        // Small-world indices: sigma = (C / C_r) / (L / L_r) and omega = L_r / L - C / C_l,
+        // against random (r) and ring-lattice (l) graphs with as many ties.
+        // A lattice too sparse for triangles, as for a tree, has no clustering to compare with; omega then keeps L_r / L.
+        let num_edge = self.adj_list.iter().map(|neighbors| neighbors.len()).sum::<usize>() / 2;
+        let (clustering_random, average_path_length_random) = if self.config.small_world_samples == 0 {
+            random_reference(self.config.n, num_edge)
+        } else {
+            self.sample_random_reference(network2_analyze)
+        };
+        let clustering_lattice = self.get_clustering_lattice(num_edge);
+        let clustering_relative_lattice = if clustering_lattice > 0.0 { self.global_clustering_watts_strogatz / clustering_lattice } else { 0.0 };
+        // A network without paths, as an edgeless layer, has no path length to compare with; the L terms are then 0.
+        let has_path_length = self.average_path_length > 0.0 && average_path_length_random.is_finite();
+        let path_length_relative_random = if has_path_length { average_path_length_random / self.average_path_length } else { 0.0 };
+        self.sigma = if has_path_length && clustering_random > 0.0 {
+            (self.global_clustering_watts_strogatz / clustering_random) / (self.average_path_length / average_path_length_random)
+        } else {
+            0.0
+        };
+        self.omega = path_length_relative_random - clustering_relative_lattice;
+
         self.adj_list.clear();
     }
 
-    /// Equivalent to `private void setShortestPathAndBetweennessCentrality()`.
-    fn set_shortest_path(&mut self, network2_analyze: &Vec<Vec<bool>>) {
-        self.shortest_path = vec![vec![-1; params::N]; params::N]; // Use -1 for unvisited
-        self.adj_list = vec![Vec::new(); params::N];
-        for i in 0..params::N {
-            for j in 0..params::N {
-                if network2_analyze[i][j] {
-                    self.adj_list[i].push(j);
+    /// Freeman degree centralization, the degree histogram, and the assortativity of the ties
+    /// by degree and by hierarchical level (Newman's r; 0 where it is undefined).
+    pub fn set_degree_metrics(&mut self, network2_analyze: &AdjacencyMatrix, level_of: &[usize]) {
+        let n = self.config.n;
+        let degree: Vec<usize> = (0..n).map(|i| network2_analyze.degree(i)).collect();
+
+        let degree_max = degree.iter().cloned().max().unwrap_or(0);
+        // Attained by the star: N - 1 agents each N - 2 short of the center.
+        let denominator = (n as f64 - 1.0) * (n as f64 - 2.0);
+        self.degree_centralization = if denominator > 0.0 {
+            degree.iter().map(|&k| (degree_max - k) as f64).sum::<f64>() / denominator
+        } else {
+            0.0
+        };
+
+        self.degree_histogram = vec![0.0; n];
+        for &k in degree.iter() {
+            self.degree_histogram[k] += 1.0 / n as f64;
+        }
+
+        let degree_f64: Vec<f64> = degree.iter().map(|&k| k as f64).collect();
+        let level_f64: Vec<f64> = level_of.iter().map(|&level| level as f64).collect();
+        self.degree_assortativity = assortativity(network2_analyze, &degree_f64);
+        self.level_assortativity = assortativity(network2_analyze, &level_f64);
+    }
+
+    /// Detects the communities of the network with Louvain and compares them with the formal units,
+    /// i.e. the subtrees of the hierarchy given by `formal_unit_of`.
+    /// The partition is kept while the network stays the same.
+    pub fn set_community_metrics(&mut self, network2_analyze: &AdjacencyMatrix, formal_unit_of: &[usize]) {
+        if self.network_partitioned.as_ref() == Some(network2_analyze) {
+            return;
+        }
+        self.network_partitioned = Some(network2_analyze.clone());
+        let adj_list = network2_analyze.to_adj_list();
+        let partition = community::louvain(&adj_list, &mut self.rng);
+        self.nmi_formal = community::normalized_mutual_information(&partition.community_of, formal_unit_of);
+        self.num_community = partition.num_community;
+        self.modularity = partition.modularity;
+        self.community_of = partition.community_of;
+    }
+
+    /// Clustering of the ring lattice with `num_edge` ties, worked out again only when the number of ties changes.
+    fn get_clustering_lattice(&mut self, num_edge: usize) -> f64 {
+        match self.clustering_lattice {
+            Some((num_edge_cached, clustering)) if num_edge_cached == num_edge => clustering,
+            _ => {
+                let clustering = clustering(&ring_lattice(self.config.n, num_edge));
+                self.clustering_lattice = Some((num_edge, clustering));
+                clustering
+            }
+        }
+    }
+
+    /// Average clustering and path length over `small_world_samples` degree-preserving
+    /// randomizations of the network (Maslov and Sneppen's double edge swaps).
+    fn sample_random_reference(&mut self, network2_analyze: &AdjacencyMatrix) -> (f64, f64) {
+        let n = self.config.n;
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        for i in 0..n {
+            for &j in &self.adj_list[i] {
+                if i < j {
+                    edges.push((i, j));
                 }
             }
         }
-    
-        for s in 0..params::N {
-            let mut stack: Vec<usize> = Vec::new();
-            let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); params::N];
-            let mut sigma = vec![0.0; params::N];
-            let mut delta = vec![0.0; params::N];
-            let mut distance: Vec<isize> = vec![-1; params::N]; // Use -1 for unvisited
-    
-            sigma[s] = 1.0;
-            distance[s] = 0;
-    
-            let mut queue = VecDeque::new();
-            queue.push_back(s);
-    
-            while let Some(v) = queue.pop_front() {
-                stack.push(v);
-                for &w in &self.adj_list[v] {
-                    if distance[w] == -1 {
-                        distance[w] = distance[v] + 1;
-                        queue.push_back(w);
-                    }
-                    if distance[w] == distance[v] + 1 {
-                        sigma[w] += sigma[v];
-                        predecessors[w].push(v);
+
+        let mut clustering = 0.0;
+        let mut average_path_length = 0.0;
+        for _ in 0..self.config.small_world_samples {
+            let mut network = network2_analyze.clone();
+            let mut edges = edges.clone();
+            if edges.len() >= 2 {
+                for _ in 0..SWAPS_PER_EDGE * edges.len() {
+                    let first = self.rng.random_range(0..edges.len());
+                    let second = self.rng.random_range(0..edges.len());
+                    let (a, b) = edges[first];
+                    let (c, d) = if self.rng.random::<bool>() { edges[second] } else { (edges[second].1, edges[second].0) };
+                    // (a, b), (c, d) -> (a, d), (c, b)
+                    if a == d || c == b || network.get(a, d) || network.get(c, b) {
+                        continue;
                     }
+                    network.set_tie(a, b, false);
+                    network.set_tie(c, d, false);
+                    network.set_tie(a, d, true);
+                    network.set_tie(c, b, true);
+                    edges[first] = (a, d);
+                    edges[second] = (c, b);
                 }
             }
-    
-            while let Some(w) = stack.pop() {
-                for &v in &predecessors[w] {
-                    if sigma[w] != 0.0 {
-                        delta[v] += (sigma[v] / sigma[w]) * (1.0 + delta[w]);
+            let (clustering_sample, average_path_length_sample) = clustering_and_path_length(&network.to_adj_list());
+            clustering += clustering_sample;
+            average_path_length += average_path_length_sample;
+        }
+        let samples = self.config.small_world_samples as f64;
+        (clustering / samples, average_path_length / samples)
+    }
+
+    /// Equivalent to `private void setShortestPathAndBetweennessCentrality()`.
+    /// After changes to the network analyzed last, only the searches from the sources whose shortest
+    /// paths the changes can reach are redone: a changed dyad leaves the distances and the shortest
+    /// paths from a source alone when both of its ends are equally far from it.
+    fn set_shortest_path(&mut self, network2_analyze: &AdjacencyMatrix) {
+        let n = self.config.n;
+        self.adj_list = network2_analyze.to_adj_list();
+        let sources = match self.network_analyzed.as_ref() {
+            Some(previous) => self.affected_sources(&changed_dyads(previous, network2_analyze)),
+            None => (0..n).collect(),
+        };
+        for s in sources {
+            let (distance, delta) = brandes(&self.adj_list, s);
+            self.dependency[s] = delta;
+            self.set_distances_from(s, &distance);
+        }
+        self.betweenness_centrality = vec![0.0; n];
+        for (s, delta) in self.dependency.iter().enumerate() {
+            for w in (0..n).filter(|&w| w != s) {
+                // Every pair is reached from both of its ends.
+                self.betweenness_centrality[w] += delta[w] / 2.0;
+            }
+        }
+    }
+
+    /// The sources whose distances or shortest paths may differ once the `changed` dyads are toggled,
+    /// judged by the distances in the network analyzed last.
+    fn affected_sources(&self, changed: &[(usize, usize)]) -> Vec<usize> {
+        (0..self.config.n)
+            .filter(|&s| changed.iter().any(|&(u, v)| self.shortest_path[s][u] != self.shortest_path[s][v]))
+            .collect()
+    }
+
+    fn set_distances_from(&mut self, s: usize, distance: &[isize]) {
+        for (i, &d) in distance.iter().enumerate() {
+            let d = u32::try_from(d).unwrap_or(UNREACHABLE);
+            self.shortest_path[s][i] = d;
+            self.shortest_path[i][s] = d;
+        }
+    }
+}
+
+/// Distance stored in `NetworkAnalyzer::shortest_path` between agents without a path.
+pub const UNREACHABLE: u32 = u32::MAX;
+
+/// Brandes' search from `s`: the distance to every node (-1 if unreachable) and the dependency of `s`
+/// on every node, i.e. the share of the shortest paths from `s` to the others that pass through it.
+fn brandes(adj_list: &[Vec<usize>], s: usize) -> (Vec<isize>, Vec<f64>) {
+    let n = adj_list.len();
+    let mut stack: Vec<usize> = Vec::new();
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut sigma = vec![0.0; n];
+    let mut delta = vec![0.0; n];
+    let mut distance: Vec<isize> = vec![-1; n]; // Use -1 for unvisited
+
+    sigma[s] = 1.0;
+    distance[s] = 0;
+
+    let mut queue = VecDeque::new();
+    queue.push_back(s);
+
+    while let Some(v) = queue.pop_front() {
+        stack.push(v);
+        for &w in &adj_list[v] {
+            if distance[w] == -1 {
+                distance[w] = distance[v] + 1;
+                queue.push_back(w);
+            }
+            if distance[w] == distance[v] + 1 {
+                sigma[w] += sigma[v];
+                predecessors[w].push(v);
+            }
+        }
+    }
+
+    while let Some(w) = stack.pop() {
+        for &v in &predecessors[w] {
+            if sigma[w] != 0.0 {
+                delta[v] += (sigma[v] / sigma[w]) * (1.0 + delta[w]);
+            }
+        }
+    }
+    (distance, delta)
+}
+
+/// The dyads (i < j) tied in one network but not in the other.
+fn changed_dyads(first: &AdjacencyMatrix, second: &AdjacencyMatrix) -> Vec<(usize, usize)> {
+    let mut changed = Vec::new();
+    for i in 0..first.len() {
+        for (w, (a, b)) in first.row(i).iter().zip(second.row(i)).enumerate() {
+            let mut rest = a ^ b;
+            while rest != 0 {
+                let j = w * u64::BITS as usize + rest.trailing_zeros() as usize;
+                if i < j {
+                    changed.push((i, j));
+                }
+                rest &= rest - 1;
+            }
+        }
+    }
+    changed
+}
+
+/// Newman's assortativity coefficient of a scalar attribute: the Pearson correlation of the
+/// attribute at the two ends of a tie, over both orientations of every tie.
+fn assortativity(network2_analyze: &AdjacencyMatrix, attribute: &[f64]) -> f64 {
+    let (mut num_end, mut sum, mut sum_square, mut sum_product) = (0.0, 0.0, 0.0, 0.0);
+    for i in 0..network2_analyze.len() {
+        for j in network2_analyze.neighbors(i) {
+            if i != j {
+                num_end += 1.0;
+                sum += attribute[i];
+                sum_square += attribute[i] * attribute[i];
+                sum_product += attribute[i] * attribute[j];
+            }
+        }
+    }
+    if num_end == 0.0 {
+        return 0.0;
+    }
+    let mean = sum / num_end;
+    let variance = sum_square / num_end - mean * mean;
+    if variance <= f64::EPSILON * mean * mean {
+        return 0.0;
+    }
+    (sum_product / num_end - mean * mean) / variance
+}
+
+/// Clustering and average path length of a random graph on `n` nodes with `num_edge` ties, in closed form:
+/// C_r = p and L_r = ln n / ln(p (n - 1)), where p is the share of the dyads that are tied.
+fn random_reference(n: usize, num_edge: usize) -> (f64, f64) {
+    let density = num_edge as f64 / (n * n.saturating_sub(1) / 2) as f64;
+    (density, (n as f64).ln() / (density * n.saturating_sub(1) as f64).ln())
+}
+
+/// Attempted swaps per edge when randomizing a network.
+const SWAPS_PER_EDGE: usize = 10;
+
+/// Watts-Strogatz clustering and average path length, measured as in `set_network_metrics`:
+/// nodes with fewer than two neighbors count as zero clustering,
+/// and the path length is averaged over the dyads connected by a path.
+fn clustering_and_path_length(adj_list: &[Vec<usize>]) -> (f64, f64) {
+    let n = adj_list.len();
+    let mut path_length_sum = 0.0;
+    let mut num_connected_dyad = 0;
+    let mut distance: Vec<isize> = vec![-1; n];
+    let mut queue = VecDeque::new();
+    for s in 0..n {
+        distance.fill(-1);
+        distance[s] = 0;
+        queue.push_back(s);
+        while let Some(v) = queue.pop_front() {
+            for &w in &adj_list[v] {
+                if distance[w] == -1 {
+                    distance[w] = distance[v] + 1;
+                    if w > s {
+                        path_length_sum += distance[w] as f64;
+                        num_connected_dyad += 1;
                     }
+                    queue.push_back(w);
                 }
             }
-    
-            for i in 0..params::N {
-                self.shortest_path[s][i] = distance[i];
-                self.shortest_path[i][s] = distance[i];
+        }
+    }
+    let average_path_length = if num_connected_dyad == 0 { 0.0 } else { path_length_sum / num_connected_dyad as f64 };
+    (clustering(adj_list), average_path_length)
+}
+
+/// Watts-Strogatz clustering alone, with nodes of fewer than two neighbors counting as zero.
+fn clustering(adj_list: &[Vec<usize>]) -> f64 {
+    let n = adj_list.len();
+    let mut is_neighbor = vec![false; n];
+    let mut clustering = 0.0;
+    for neighbors in adj_list {
+        let degree = neighbors.len();
+        if degree >= 2 {
+            for &j in neighbors {
+                is_neighbor[j] = true;
+            }
+            let mut triangles = 0;
+            for &j in neighbors {
+                triangles += adj_list[j].iter().filter(|&&k| is_neighbor[k]).count();
+            }
+            for &j in neighbors {
+                is_neighbor[j] = false;
             }
+            // Each triangle through the node was counted from both of its other corners.
+            clustering += (triangles / 2) as f64 / (degree * (degree - 1) / 2) as f64;
         }
     }
-    
+    clustering / n as f64
+}
+
+/// Ring lattice of `n` nodes with `num_edge` ties: every node is tied to its nearest
+/// neighbors on the ring, and the ties of the outermost ring that does not fit whole
+/// go to the first nodes.
+fn ring_lattice(n: usize, num_edge: usize) -> Vec<Vec<usize>> {
+    let mut adj_list = vec![Vec::new(); n];
+    let mut remaining = num_edge;
+    let mut distance = 1;
+    while remaining > 0 && distance <= n / 2 {
+        // On an even ring the opposite nodes are a single tie apart.
+        let ties = if 2 * distance == n { n / 2 } else { n };
+        for i in 0..ties.min(remaining) {
+            let j = (i + distance) % n;
+            adj_list[i].push(j);
+            adj_list[j].push(i);
+        }
+        remaining = remaining.saturating_sub(ties);
+        distance += 1;
+    }
+    adj_list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::{LAYER_COMBINED, LAYER_FORMAL};
+    use rand::SeedableRng;
+
+    fn config(n: usize) -> Arc<ExperimentConfig> {
+        let mut config = ExperimentConfig { n, ..ExperimentConfig::default() };
+        config.set_second_order_params();
+        Arc::new(config)
+    }
+
+    fn analyzer(config: &Arc<ExperimentConfig>) -> NetworkAnalyzer {
+        NetworkAnalyzer::new(config, &ScenarioRng::seed_from_u64(0), LAYER_COMBINED)
+    }
+
+    /// Network on `n` nodes from a list of ties.
+    fn network(n: usize, ties: &[(usize, usize)]) -> AdjacencyMatrix {
+        let mut network = AdjacencyMatrix::new(n);
+        for &(i, j) in ties {
+            network.set_tie(i, j, true);
+        }
+        network
+    }
+
+    fn star(n: usize) -> AdjacencyMatrix {
+        network(n, &(1..n).map(|i| (0, i)).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn betweenness_counts_the_shortest_paths_through_each_node() {
+        let n = 5;
+        let config = config(n);
+        let path: Vec<(usize, usize)> = (1..n).map(|i| (i - 1, i)).collect();
+        let cycle = [(0, 1), (1, 2), (2, 3), (3, 0), (3, 4)];
+        // Along a path, node k lies between the k nodes before it and the n - 1 - k after it.
+        // Around the 4-cycle, opposite nodes have two shortest paths, as do 4 and 1 through 3 and either 0 or 2.
+        // Centralization is relative to the star, whose shortfall sums to (n - 1)^2 (n - 2) / 2 = 24.
+        let expected = [
+            (network(n, &path), [0.0, 3.0, 4.0, 3.0, 0.0], 10.0 / 24.0),
+            (network(n, &cycle), [1.0, 0.5, 1.0, 3.5, 0.0], 11.5 / 24.0),
+            (star(n), [6.0, 0.0, 0.0, 0.0, 0.0], 1.0),
+        ];
+        for (network, betweenness, centralization) in expected {
+            let mut analyzer = analyzer(&config);
+            analyzer.set_network_metrics(&network);
+            for (actual, expected) in analyzer.betweenness_centrality.iter().zip(betweenness) {
+                assert!((actual - expected).abs() < 1e-12, "{:?} != {:?}", analyzer.betweenness_centrality, betweenness);
+            }
+            assert!((analyzer.get_betweenness_centralization() - centralization).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn degree_metrics_of_a_star_and_of_cliques_apart() {
+        let n = 7;
+        let config = config(n);
+        let mut analyzer = analyzer(&config);
+        // Ties of the star join the center, at level 0, to leaves, at level 1.
+        analyzer.set_degree_metrics(&star(n), &[0, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(analyzer.get_degree_centralization(), 1.0);
+        assert!((analyzer.get_degree_assortativity() + 1.0).abs() < 1e-12);
+        assert!((analyzer.get_level_assortativity() + 1.0).abs() < 1e-12);
+        assert!((analyzer.get_degree_histogram()[1] - 6.0 / 7.0).abs() < 1e-12);
+        assert!((analyzer.get_degree_histogram()[6] - 1.0 / 7.0).abs() < 1e-12);
+
+        // A triangle and a clique of four: every tie joins agents of the same degree and the same level.
+        let cliques = network(n, &[(0, 1), (0, 2), (1, 2), (3, 4), (3, 5), (3, 6), (4, 5), (4, 6), (5, 6)]);
+        analyzer.set_degree_metrics(&cliques, &[0, 0, 0, 1, 1, 1, 1]);
+        assert!((analyzer.get_degree_assortativity() - 1.0).abs() < 1e-12);
+        assert!((analyzer.get_level_assortativity() - 1.0).abs() < 1e-12);
+        assert!((analyzer.get_degree_histogram()[2] - 3.0 / 7.0).abs() < 1e-12);
+        assert!((analyzer.get_degree_histogram()[3] - 4.0 / 7.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn degree_histograms_sum_to_one() {
+        let n = 30;
+        let config = config(n);
+        let mut analyzer = analyzer(&config);
+        let mut rng = ScenarioRng::seed_from_u64(8);
+        let mut network = AdjacencyMatrix::new(n);
+        for _ in 0..60 {
+            let (i, j) = (rng.random_range(0..n), rng.random_range(0..n));
+            if i != j {
+                network.set_tie(i, j, true);
+            }
+            analyzer.set_degree_metrics(&network, &vec![0; n]);
+            assert!((analyzer.get_degree_histogram().iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            // A single level has no variance to correlate.
+            assert_eq!(analyzer.get_level_assortativity(), 0.0);
+        }
+    }
+
+    #[test]
+    fn path_metrics_of_a_disconnected_network_count_only_connected_dyads() {
+        let n = 7;
+        let config = config(n);
+        let mut analyzer = analyzer(&config);
+        // Two paths of three and an isolated agent.
+        analyzer.set_network_metrics(&network(n, &[(0, 1), (1, 2), (3, 4), (4, 5)]));
+        assert_eq!(analyzer.get_num_component(), 3);
+        assert!(analyzer.is_disconnected());
+        assert_eq!(analyzer.get_giant_component_share(), 3.0 / 7.0);
+        assert!((analyzer.get_average_path_length() - 8.0 / 6.0).abs() < 1e-12);
+        // The ends of a path are at 1 and 2 from the others, its middle at 1 from both.
+        assert!((analyzer.get_shortest_path_variance() - 1.0 / 6.0).abs() < 1e-12);
+        // Harmonic closeness of 1.5 at the ends, 2 in the middle and 0 when isolated;
+        // the star falls short by (n - 1) (n - 2) / 2 = 15 in all.
+        assert!((analyzer.get_harmonic_centralization() - 4.0 / 15.0).abs() < 1e-12);
+
+        analyzer.set_network_metrics(&star(n));
+        assert!(!analyzer.is_disconnected());
+        assert!((analyzer.get_harmonic_centralization() - 1.0).abs() < 1e-12);
+
+        analyzer.set_network_metrics(&AdjacencyMatrix::new(n));
+        assert_eq!(analyzer.get_num_component(), n);
+        assert_eq!(analyzer.get_harmonic_centralization(), 0.0);
+        assert_eq!(analyzer.get_shortest_path_variance(), 0.0);
+        assert_eq!(analyzer.get_average_path_length(), 0.0);
+    }
+
+    #[test]
+    fn full_ring_lattices_cluster_as_in_closed_form() {
+        let n = 20;
+        for k in 1..5 {
+            // Every node tied to the k nearest on either side: C = 3 (k - 1) / (2 (2k - 1)).
+            let expected = 3.0 * (k as f64 - 1.0) / (2.0 * (2.0 * k as f64 - 1.0));
+            assert!((clustering(&ring_lattice(n, k * n)) - expected).abs() < 1e-12, "k = {}", k);
+        }
+    }
+
+    #[test]
+    fn omega_is_finite_on_a_tree() {
+        let n = 15;
+        let config = config(n);
+        let mut analyzer = analyzer(&config);
+        let tree: Vec<(usize, usize)> = (1..n).map(|i| ((i - 1) / 2, i)).collect();
+        analyzer.set_network_metrics(&network(n, &tree));
+        assert_eq!(analyzer.get_global_clustering_watts_strogatz(), 0.0);
+        assert!(analyzer.get_omega().is_finite());
+        // Against a random graph with as many ties as the tree.
+        let (_, average_path_length_random) = random_reference(n, n - 1);
+        assert_eq!(analyzer.get_omega(), average_path_length_random / analyzer.get_average_path_length());
+    }
+
+    #[test]
+    fn small_world_indices_are_zero_on_an_edgeless_layer() {
+        let n = 15;
+        let config = config(n);
+        let mut analyzer = analyzer(&config);
+        analyzer.set_network_metrics(&AdjacencyMatrix::new(n));
+        assert_eq!(analyzer.get_average_path_length(), 0.0);
+        assert_eq!(analyzer.get_sigma(), 0.0);
+        assert_eq!(analyzer.get_omega(), 0.0);
+
+        let config = Arc::new(ExperimentConfig { small_world_samples: 3, ..(*config).clone() });
+        let mut analyzer = NetworkAnalyzer::new(&config, &ScenarioRng::seed_from_u64(0), LAYER_FORMAL);
+        analyzer.set_network_metrics(&AdjacencyMatrix::new(n));
+        assert_eq!(analyzer.get_sigma(), 0.0);
+        assert_eq!(analyzer.get_omega(), 0.0);
+    }
+
+    #[test]
+    fn updating_after_rewirings_matches_a_fresh_analysis() {
+        let n = 40;
+        let config = config(n);
+        let mut rng = ScenarioRng::seed_from_u64(3);
+        // A ring with chords, sparse enough that a rewiring leaves some sources unaffected.
+        let mut network = network(n, &(0..n).map(|i| (i, (i + 1) % n)).collect::<Vec<_>>());
+        for _ in 0..10 {
+            network.set_tie(rng.random_range(0..n / 2), rng.random_range(n / 2..n), true);
+        }
+        let mut reused = analyzer(&config);
+        reused.set_network_metrics(&network);
+        let mut num_search_skipped = 0;
+        for step in 0..60 {
+            // Every other step, a few ties are rewired: one broken and another formed for each.
+            for _ in 0..step % 2 * (1 + step % 3) {
+                let (i, j) = (rng.random_range(0..n), rng.random_range(0..n));
+                if network.get(i, j) {
+                    network.set_tie(i, j, false);
+                }
+                let (i, j) = (rng.random_range(0..n), rng.random_range(0..n));
+                if i != j {
+                    network.set_tie(i, j, true);
+                }
+            }
+            let changed = changed_dyads(reused.network_analyzed.as_ref().unwrap(), &network);
+            if !changed.is_empty() {
+                num_search_skipped += n - reused.affected_sources(&changed).len();
+            }
+            reused.set_network_metrics(&network);
+            let mut fresh = analyzer(&config);
+            fresh.set_network_metrics(&network);
+
+            assert_eq!(reused.shortest_path, fresh.shortest_path, "step {}", step);
+            assert_eq!(reused.betweenness_centrality, fresh.betweenness_centrality, "step {}", step);
+            assert_eq!(reused.centralization_betweenness.to_bits(), fresh.centralization_betweenness.to_bits());
+            assert_eq!(reused.average_path_length.to_bits(), fresh.average_path_length.to_bits());
+            assert_eq!(reused.network_efficiency.to_bits(), fresh.network_efficiency.to_bits());
+            assert_eq!(reused.global_clustering_watts_strogatz.to_bits(), fresh.global_clustering_watts_strogatz.to_bits());
+            assert_eq!(reused.num_component, fresh.num_component);
+        }
+        assert!(num_search_skipped > 0);
+    }
 }
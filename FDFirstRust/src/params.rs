@@ -1,159 +1,709 @@
-use std::env;
-use once_cell::sync::OnceCell;
-use once_cell::sync::Lazy;
-
-pub static MAX_THREAD:usize = 999;
-
-pub static RUN_ID: &str = "DATT";
-pub static RUN_DESC: &str = "";
-
-pub static GET_GRAPH: bool = true;
-pub static GET_GRAPH_INIT: bool = false;
-pub static GET_MAT: bool = false;
-
-pub static ITERATION: usize = 10_000;
-pub static LINK_LEVEL: bool = false;
-pub static LIMIT_LEVEL: bool = false;
-pub static P_ADDITION: f64 = 0.0;
-
-pub static TIME: usize = 101;
-
-pub static INFORMAL_MAX_NUM: isize = 10;
-pub static INFORMAL_INITIAL_PROP: f64 = 0.5;
-pub static INFORMAL_REWIRING_PROP: f64 = 0.02;
-
-pub static N: usize = 250;
-// 30 * 4 = 24 * 5 = 20 * 6 = 15 * 8 = 12 * 10 = 10 * 12
-// pub static M_OF_BUNDLE: usize = 20;
-// pub static M_IN_BUNDLE: usize = 3;
-// 100 * 1; 20 * 5; 10 * 10; 
-pub static M_OF_BUNDLE: usize = 20;
-pub static M_IN_BUNDLE: usize = 5;
-
-// pub static SPAN: [usize; 7] = [2, 3, 4, 5, 6, 7, 8];
-// pub static SPAN: [usize; 3] = [2, 5, 8];
-pub static SPAN: [usize; 2] = [2, 8];
-
-// pub static ENFORCEMENT: [f64; 4] = [0.0, 0.5, 0.8, 1.0];
-// pub static ENFORCEMENT: [f64; 2] = [0.0, 1.0];
-pub static ENFORCEMENT: [f64; 1] = [1.0];
-
-// pub static TURBULENCE_RATE: [f64; 3] = [0.0, 0.1, 0.1];
-// pub static TURBULENCE_INTERVAL: [usize; 3] = [TIME, 25, 100];
-pub static TURBULENCE_RATE: [f64; 1] = [0.0];
-pub static TURBULENCE_INTERVAL: [usize; 1] = [TIME];
-
-// pub static TURNOVER_RATE: [f64; 3] = [0.0, 0.01, 0.1];
-pub static TURNOVER_RATE: [f64; 1] = [0.0];
-
-pub static LENGTH_SPAN: usize = SPAN.len();
-pub static LENGTH_ENFORCEMENT: usize = ENFORCEMENT.len();
-pub static LENGTH_TURBULENCE: usize = TURBULENCE_RATE.len();
-pub static LENGTH_TURNOVER: usize = TURNOVER_RATE.len();
-
-pub static P_LEARNING: f64 = 0.3;
-
-//SECOND-ORDER PARAMETERS 
-pub static M: usize = M_OF_BUNDLE * M_IN_BUNDLE;
-pub static M_N: usize = M * N;
-pub static N_DYAD: usize = N * (N - 1) / 2;
-pub static N_DYAD_F64: f64 = N_DYAD as f64;
-pub static M_N_DYAD: f64 = (M * N_DYAD) as f64;
-
-pub static NUM_SOCIAL_DYNAMICS: usize = 2;
-
-pub static INFORMAL_INITIAL_NUM: usize = (INFORMAL_MAX_NUM as f64 * N as f64 / 2.0 * INFORMAL_INITIAL_PROP) as usize;
-pub static INFORMAL_REWIRING_NUM: usize = (INFORMAL_INITIAL_NUM as f64 * INFORMAL_REWIRING_PROP) as usize;
-pub static NUM_ADDITION: usize = (N_DYAD as f64 * P_ADDITION) as usize;
-
-pub static CLOSENESS_CENTRALIZATION_DENOMINATOR: f64 = (N as f64 - 1.0) * (N as f64- 2.0)  / (2.0 * N as f64 - 3.0);
-pub static CLUSTERING_COEFFICIENT_RANDOM: f64 = (INFORMAL_INITIAL_NUM + N - 1) as f64 / N_DYAD_F64;
-pub static CLUSTERING_COEFFICIENT_RANDOM_NO_SOCIAL_DYNAMICS: f64 = (N - 1) as f64 / N_DYAD_F64;
-
-pub static TRIADIC_CENTRALIZATION_DENOMINATOR: f64 = (N as f64 - 1.0) * ((N as f64 - 1.0) - 2.0);
-
-pub static AVERAGE_PATH_LENGTH_RANDOM: Lazy<f64> = Lazy::new(|| (N as f64).ln() / (CLUSTERING_COEFFICIENT_RANDOM * (N-1) as f64).ln());
-pub static AVERAGE_PATH_LENGTH_RANDOM_NO_SOCIAL_DYNAMICS: Lazy<f64> = Lazy::new(|| (N as f64).ln() / (CLUSTERING_COEFFICIENT_RANDOM_NO_SOCIAL_DYNAMICS * (N-1) as f64).ln());
-
-//Will be printed in the reverse order
-pub static RESULT_SHAPE: [usize; 6] = [
-    NUM_SOCIAL_DYNAMICS,
-    LENGTH_SPAN as usize,
-    LENGTH_ENFORCEMENT as usize,
-    LENGTH_TURBULENCE as usize,
-    LENGTH_TURNOVER as usize,
-    TIME,
-];
-
-
-pub static PARAMS_INDEX_COMBINATIONS: OnceCell<Vec<(usize, usize, usize, usize, usize)>> = OnceCell::new();
-pub static PARAMS_INDEX_COMBINATIONS_WITH_TIME: OnceCell<Vec<(usize, usize, usize, usize, usize, usize)>> = OnceCell::new();
-pub static PARAM_STRING: Lazy<String> = Lazy::new(|| 
-    format!(
-        "I{}_T{}_Ll{}_Lm{}_PA{}_DM{}_r({}&{})_N{}M({}in{})_S{}E{}Turb{}Turn{}PL{}",
-        ITERATION,
-        TIME,
-        if LINK_LEVEL { "1" } else { "0" },
-        if LIMIT_LEVEL { "1" } else { "0" },
-        P_ADDITION,
-        INFORMAL_MAX_NUM,
-        INFORMAL_INITIAL_PROP,
-        INFORMAL_REWIRING_PROP,
-        N,
-        M_IN_BUNDLE,
-        M_OF_BUNDLE,
-        SPAN.len(),
-        ENFORCEMENT.len(),
-        TURBULENCE_RATE.len(),
-        TURNOVER_RATE.len(),
-        // SPAN.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("&"),
-        // ENFORCEMENT.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("&"),
-        // TURBULENCE_RATE.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("&"),
-        // TURBULENCE_INTERVAL.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("&"),
-        // TURNOVER_RATE.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("&"),
-        P_LEARNING
-    ).to_string());
-
-pub static FILE_NAME: Lazy<String> = Lazy::new(|| format!("{}_{}", RUN_ID, *PARAM_STRING));
-pub static FILE_PATH: Lazy<String> = Lazy::new(|| env::current_dir().unwrap().to_str().unwrap().to_string());
-
-pub fn initialize_once_cells() {
-
-    let mut combinations = Vec::new();
-    let mut combinations_with_time = Vec::new();
-
-    for i_social_dynamics in 0..NUM_SOCIAL_DYNAMICS {
-        for i_span in 0..LENGTH_SPAN {
-            for i_enforcement in 0..LENGTH_ENFORCEMENT {
-                for i_turbulence in 0..LENGTH_TURBULENCE {
-                    for i_turnover in 0..LENGTH_TURNOVER {
-                        combinations.push((
-                            i_social_dynamics,
-                            i_span,
-                            i_enforcement,
-                            i_turbulence,
-                            i_turnover,
-                        ));
-                        for t in 0..TIME {
-                            combinations_with_time.push((
+use std::cmp;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use crate::scenario::ScenarioRng;
+use crate::learning_rule::{self, LearningRule, LEARNING_RULES};
+use crate::social_dynamics::{self, SocialDynamics, SOCIAL_DYNAMICS};
+use crate::task_environment::{self, NkInteraction, TaskEnvironmentKind, NK_INTERACTIONS, TASK_ENVIRONMENTS};
+
+pub type ParamsIndex = (usize, usize, usize, usize, usize);
+pub type ParamsIndexWithTime = (usize, usize, usize, usize, usize, usize);
+
+// Arms compared in every iteration: systematic, random and no rewiring.
+pub const ARM_SC: usize = 0;
+pub const ARM_RR: usize = 1;
+pub const ARM_NR: usize = 2;
+pub const NUM_ARMS: usize = 3;
+pub const ARM_NAMES: [&str; NUM_ARMS] = ["sc", "rr", "nr"];
+
+// Layers of the network along the layer axis of the network metrics: all ties, enforced ties and the rest.
+pub const LAYER_COMBINED: usize = 0;
+pub const LAYER_FORMAL: usize = 1;
+pub const LAYER_INFORMAL: usize = 2;
+pub const NUM_LAYERS: usize = 3;
+
+/// A disjoint slice of the iterations of a design, run on its own by `flat run --shard k/n`:
+/// the iterations `i` with `i % count == index`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Default for Shard {
+    /// The whole run.
+    fn default() -> Self {
+        Shard { index: 0, count: 1 }
+    }
+}
+
+impl Shard {
+    pub fn contains(&self, iteration: usize) -> bool {
+        iteration % self.count == self.index
+    }
+
+    pub fn is_whole(&self) -> bool {
+        self.count == 1
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    /// Parses `k/n`, the k-th of n shards counting from 1.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("`{}` is not of the form k/n with 1 <= k <= n", s);
+        let (k, n) = s.split_once('/').ok_or_else(invalid)?;
+        let k: usize = k.trim().parse().map_err(|_| invalid())?;
+        let n: usize = n.trim().parse().map_err(|_| invalid())?;
+        if k == 0 || k > n {
+            return Err(invalid());
+        }
+        Ok(Shard { index: k - 1, count: n })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index + 1, self.count)
+    }
+}
+
+/// Experiment design read at startup from a TOML or JSON file.
+/// Only the first-order parameters are read; the second-order ones are derived
+/// by `set_second_order_params` and never appear in the file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExperimentConfig {
+    pub max_thread: usize,
+
+    pub run_id: String,
+    pub run_desc: String,
+    pub output_dir: PathBuf,
+
+    // Master seed of every random stream; drawn afresh when left out of the design file,
+    // within the integers TOML can hold, so that the printed seed can be passed back.
+    pub seed: u64,
+    // Whether `seed` was drawn rather than given by the design.
+    #[serde(skip)] pub is_seed_drawn: bool,
+
+    pub get_graph_init: bool,
+
+    // Iterations between two checkpoints of `flat run`; 0 turns checkpointing off.
+    pub checkpoint_interval: usize,
+
+    // Slice of the iterations run by this process; see `Shard`.
+    pub shard: Shard,
+
+    pub iteration: usize,
+    pub link_level: bool,
+    pub limit_level: bool,
+    pub p_addition: f64,
+
+    pub time: usize,
+
+    pub informal_max_num: isize,
+    pub informal_initial_prop: f64,
+    pub informal_rewiring_prop: f64,
+
+    pub n: usize,
+    // 30 * 4 = 24 * 5 = 20 * 6 = 15 * 8 = 12 * 10 = 10 * 12
+    // 100 * 1; 20 * 5; 10 * 10;
+    pub m_of_bundle: usize,
+    pub m_in_bundle: usize,
+
+    // How beliefs pay off, by its name in `task_environment::TASK_ENVIRONMENTS`: all-or-nothing bundles,
+    // or an NK landscape in which each dimension interacts with `nk_k` others as laid out by `nk_interaction`.
+    pub task_environment: String,
+    pub nk_k: usize,
+    pub nk_interaction: String,
+
+    // Tie-formation mechanisms compared, by their names in `social_dynamics::SOCIAL_DYNAMICS`.
+    pub social_dynamics: Vec<String>,
+    pub span: Vec<usize>,
+    pub enforcement: Vec<f64>,
+    pub turbulence_rate: Vec<f64>,
+    pub turbulence_interval: Vec<usize>,
+    pub turnover_rate: Vec<f64>,
+
+    pub p_learning: f64,
+
+    // How agents learn, by its name in `learning_rule::LEARNING_RULES`; the same in every combination.
+    pub learning_rule: String,
+    // Rates of the organizational code rule: agents from the code, and the code from the agents who outperform it.
+    pub p_socialization: f64,
+    pub p_code_learning: f64,
+
+    // Homophily: how far tie formation follows similarity rather than chance (0 rewires at random, 1 by similarity alone),
+    // and the share of that similarity taken from performance rather than from beliefs.
+    pub homophily_strength: f64,
+    pub homophily_performance_weight: f64,
+
+    // Random reference graphs sampled for sigma and omega at every measurement;
+    // 0 uses the analytic approximations of a random graph instead.
+    pub small_world_samples: usize,
+
+    // Also compute the network metrics on the formal and the informal layer alone,
+    // which adds a layer axis (combined, formal, informal) before the time axis of those metrics.
+    pub layered_metrics: bool,
+
+    // Record the degree distribution of every network layer at every time step (`r_dhis_*`),
+    // along a bin axis of length N after the time axis.
+    pub degree_histograms: bool,
+
+    //SECOND-ORDER PARAMETERS
+    #[serde(skip)] pub length_span: usize,
+    #[serde(skip)] pub length_enforcement: usize,
+    #[serde(skip)] pub length_turbulence: usize,
+    #[serde(skip)] pub length_turnover: usize,
+
+    #[serde(skip)] pub m: usize,
+    #[serde(skip)] pub m_n: usize,
+    #[serde(skip)] pub n_dyad: usize,
+
+    #[serde(skip)] pub num_social_dynamics: usize,
+
+    #[serde(skip)] pub informal_initial_num: usize,
+    #[serde(skip)] pub informal_rewiring_num: usize,
+    #[serde(skip)] pub num_addition: usize,
+
+    #[serde(skip)] pub closeness_centralization_denominator: f64,
+    #[serde(skip)] pub triadic_centralization_denominator: f64,
+    #[serde(skip)] pub betweenness_centralization_denominator: f64,
+
+    //Will be printed in the reverse order
+    #[serde(skip)] pub result_shape: Vec<usize>,
+    #[serde(skip)] pub result_shape_layered: Vec<usize>,
+
+    #[serde(skip)] pub params_index_combinations: Vec<ParamsIndex>,
+    #[serde(skip)] pub params_index_combinations_with_time: Vec<ParamsIndexWithTime>,
+    #[serde(skip)] pub param_string: String,
+    #[serde(skip)] pub file_name: String,
+}
+
+impl Default for ExperimentConfig {
+    /// The design that used to be hard-coded in this file.
+    fn default() -> Self {
+        let time = 101;
+        let mut config = ExperimentConfig {
+            max_thread: 999,
+            run_id: "DATT".to_string(),
+            run_desc: String::new(),
+            output_dir: PathBuf::from("."),
+            seed: rand::random_range(0..=i64::MAX as u64),
+            is_seed_drawn: true,
+            get_graph_init: false,
+            checkpoint_interval: 500,
+            shard: Shard::default(),
+            iteration: 10_000,
+            link_level: false,
+            limit_level: false,
+            p_addition: 0.0,
+            time,
+            informal_max_num: 10,
+            informal_initial_prop: 0.5,
+            informal_rewiring_prop: 0.02,
+            n: 250,
+            m_of_bundle: 20,
+            m_in_bundle: 5,
+            task_environment: "bundles".to_string(),
+            nk_k: 0,
+            nk_interaction: "random".to_string(),
+            social_dynamics: vec!["network_closure".to_string(), "preferential_attachment".to_string(), "homophily".to_string(), "performance_seeking".to_string()],
+            span: vec![2, 8],
+            enforcement: vec![1.0],
+            turbulence_rate: vec![0.0],
+            turbulence_interval: vec![time],
+            turnover_rate: vec![0.0],
+            p_learning: 0.3,
+            learning_rule: "superior_majority".to_string(),
+            p_socialization: 0.1,
+            p_code_learning: 0.1,
+            homophily_strength: 1.0,
+            homophily_performance_weight: 0.0,
+            small_world_samples: 0,
+            layered_metrics: false,
+            degree_histograms: false,
+            length_span: 0,
+            length_enforcement: 0,
+            length_turbulence: 0,
+            length_turnover: 0,
+            m: 0,
+            m_n: 0,
+            n_dyad: 0,
+            num_social_dynamics: 0,
+            informal_initial_num: 0,
+            informal_rewiring_num: 0,
+            num_addition: 0,
+            closeness_centralization_denominator: 0.0,
+            triadic_centralization_denominator: 0.0,
+            betweenness_centralization_denominator: 0.0,
+            result_shape: Vec::new(),
+            result_shape_layered: Vec::new(),
+            params_index_combinations: Vec::new(),
+            params_index_combinations_with_time: Vec::new(),
+            param_string: String::new(),
+            file_name: String::new(),
+        };
+        config.set_second_order_params();
+        config
+    }
+}
+
+impl ExperimentConfig {
+    /// Reads the first-order parameters from `path` and derives the rest.
+    /// `*.json` files are parsed as JSON; anything else as TOML.
+    /// Parameters missing from the file keep their default values.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::load(Some(path.as_ref()), &[])
+    }
+
+    /// Reads the design from `path` (or the defaults when `None`), then applies
+    /// `overrides` of the form `key=value`, where `value` is written as in TOML
+    /// (`n=100`, `span=[2,4]`, `run_id="X"`; bare words are taken as strings).
+    pub fn load(path: Option<&Path>, overrides: &[String]) -> io::Result<Self> {
+        let mut table = match path {
+            Some(path) => {
+                let contents = fs::read_to_string(path)?;
+                match path.extension().and_then(|ext| ext.to_str()) {
+                    Some("json") => serde_json::from_str::<toml::Table>(&contents)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+                    _ => toml::from_str::<toml::Table>(&contents)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+                }
+            }
+            None => toml::Table::new(),
+        };
+
+        for assignment in overrides {
+            let (key, value) = assignment.split_once('=').ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("override `{}` is not of the form key=value", assignment))
+            })?;
+            let value = match toml::from_str::<toml::Table>(&format!("value = {}", value.trim())) {
+                Ok(mut parsed) => parsed.remove("value").unwrap(),
+                Err(_) => toml::Value::String(value.trim().to_string()),
+            };
+            table.insert(key.trim().to_string(), value);
+        }
+
+        let is_seed_drawn = !table.contains_key("seed");
+        let mut config: ExperimentConfig = table
+            .try_into()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        config.is_seed_drawn = is_seed_drawn;
+        config.set_second_order_params();
+        Ok(config)
+    }
+
+    /// Where the result file of `flat run` is written.
+    pub fn result_file_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}.h5", self.file_name))
+    }
+
+    /// Where `flat run` keeps the partial results it resumes from with `--resume`.
+    pub fn checkpoint_file_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}.checkpoint.json", self.file_name))
+    }
+
+    /// Whether two designs produce the same results given the same seed,
+    /// i.e. whether they differ at most in the seed and in how and where the run is carried out.
+    pub fn same_design(&self, other: &ExperimentConfig) -> bool {
+        let outcome_relevant = |config: &ExperimentConfig| {
+            let mut value = serde_json::to_value(config).expect("The design is always serializable");
+            if let Some(fields) = value.as_object_mut() {
+                for field in ["max_thread", "run_desc", "output_dir", "seed", "get_graph_init", "checkpoint_interval"] {
+                    fields.remove(field);
+                }
+            }
+            value
+        };
+        outcome_relevant(self) == outcome_relevant(other)
+    }
+
+    /// Where `flat sample` writes the network CSVs.
+    pub fn sample_dir_path(&self) -> PathBuf {
+        self.output_dir.join(&self.param_string)
+    }
+
+    /// The social dynamics at position `i_social_dynamics` of the design, whose names `check_validity` has checked.
+    pub fn social_dynamics_at(&self, i_social_dynamics: usize) -> &'static dyn SocialDynamics {
+        social_dynamics::find(&self.social_dynamics[i_social_dynamics]).expect("unknown social dynamics")
+    }
+
+    /// The learning rule of the design, whose name `check_validity` has checked.
+    pub fn get_learning_rule(&self) -> &'static dyn LearningRule {
+        learning_rule::find(&self.learning_rule).expect("unknown learning rule")
+    }
+
+    /// The task environment of the design, whose name `check_validity` has checked.
+    pub fn get_task_environment(&self) -> &'static dyn TaskEnvironmentKind {
+        task_environment::find(&self.task_environment).expect("unknown task environment")
+    }
+
+    /// The NK interaction structure of the design, whose name `check_validity` has checked.
+    pub fn get_nk_interaction(&self) -> &'static dyn NkInteraction {
+        task_environment::find_interaction(&self.nk_interaction).expect("unknown NK interaction")
+    }
+
+    /// The generator of one arm of one parameter combination in one iteration.
+    /// Every (iteration, combination, arm) gets its own ChaCha stream under the master seed,
+    /// so any single trajectory can be regenerated regardless of thread scheduling.
+    pub fn rng_for(&self, iteration: usize, combination: usize, arm: usize) -> ScenarioRng {
+        let mut rng = ScenarioRng::seed_from_u64(self.seed);
+        let stream = (iteration * self.params_index_combinations.len() + combination) * NUM_ARMS + arm;
+        rng.set_stream(stream as u64);
+        rng
+    }
+
+    /// Recomputes every second-order parameter from the first-order ones.
+    /// Call again after changing any first-order field.
+    pub fn set_second_order_params(&mut self) {
+        let n = self.n;
+
+        self.length_span = self.span.len();
+        self.length_enforcement = self.enforcement.len();
+        self.length_turbulence = self.turbulence_rate.len();
+        self.length_turnover = self.turnover_rate.len();
+
+        self.m = self.m_of_bundle * self.m_in_bundle;
+        self.m_n = self.m * n;
+        self.n_dyad = n * n.saturating_sub(1) / 2;
+
+        self.num_social_dynamics = self.social_dynamics.len();
+
+        self.informal_initial_num = (self.informal_max_num as f64 * n as f64 / 2.0 * self.informal_initial_prop) as usize;
+        self.informal_rewiring_num = (self.informal_initial_num as f64 * self.informal_rewiring_prop) as usize;
+        self.num_addition = (self.n_dyad as f64 * self.p_addition) as usize;
+
+        self.closeness_centralization_denominator = (n as f64 - 1.0) * (n as f64 - 2.0) / (2.0 * n as f64 - 3.0);
+        self.triadic_centralization_denominator = (n as f64 - 1.0) * ((n as f64 - 1.0) - 2.0);
+        // Attained by the star, whose center lies on every path between the (N - 1)(N - 2) / 2 pairs of leaves.
+        self.betweenness_centralization_denominator = (n as f64 - 1.0) * (n as f64 - 1.0) * (n as f64 - 2.0) / 2.0;
+
+        self.result_shape = vec![
+            self.num_social_dynamics,
+            self.length_span,
+            self.length_enforcement,
+            self.length_turbulence,
+            self.length_turnover,
+            self.time,
+        ];
+        self.result_shape_layered = self.result_shape.clone();
+        self.result_shape_layered.insert(self.result_shape.len() - 1, NUM_LAYERS);
+
+        self.params_index_combinations.clear();
+        self.params_index_combinations_with_time.clear();
+        for i_social_dynamics in 0..self.num_social_dynamics {
+            for i_span in 0..self.length_span {
+                for i_enforcement in 0..self.length_enforcement {
+                    for i_turbulence in 0..self.length_turbulence {
+                        for i_turnover in 0..self.length_turnover {
+                            self.params_index_combinations.push((
                                 i_social_dynamics,
                                 i_span,
                                 i_enforcement,
                                 i_turbulence,
                                 i_turnover,
-                                t,
                             ));
+                            for t in 0..self.time {
+                                self.params_index_combinations_with_time.push((
+                                    i_social_dynamics,
+                                    i_span,
+                                    i_enforcement,
+                                    i_turbulence,
+                                    i_turnover,
+                                    t,
+                                ));
+                            }
                         }
                     }
                 }
             }
         }
+
+        // Names `check_validity` has yet to check stand for themselves.
+        let social_dynamics_codes: Vec<&str> = self.social_dynamics.iter()
+            .map(|name| social_dynamics::find(name).map_or(name.as_str(), |social_dynamics| social_dynamics.code()))
+            .collect();
+        let learning_rule_code = learning_rule::find(&self.learning_rule).map_or(self.learning_rule.as_str(), |learning_rule| learning_rule.code());
+        let task_environment_code = task_environment::find(&self.task_environment).map_or(self.task_environment.clone(), |kind| kind.code(self));
+        self.param_string = format!(
+            "I{}_T{}_Ll{}_Lm{}_PA{}_DM{}_r({}&{})_N{}M({}in{})_S{}E{}Turb{}Turn{}PL{}_D({})_{}_{}",
+            self.iteration,
+            self.time,
+            if self.link_level { "1" } else { "0" },
+            if self.limit_level { "1" } else { "0" },
+            self.p_addition,
+            self.informal_max_num,
+            self.informal_initial_prop,
+            self.informal_rewiring_prop,
+            n,
+            self.m_in_bundle,
+            self.m_of_bundle,
+            self.length_span,
+            self.length_enforcement,
+            self.length_turbulence,
+            self.length_turnover,
+            self.p_learning,
+            social_dynamics_codes.join("&"),
+            learning_rule_code,
+            task_environment_code
+        );
+        self.file_name = format!("{}_{}", self.run_id, self.param_string);
+        if !self.shard.is_whole() {
+            self.file_name += &format!("_shard{}of{}", self.shard.index + 1, self.shard.count);
+        }
     }
 
-    PARAMS_INDEX_COMBINATIONS.set(combinations).unwrap();
-    PARAMS_INDEX_COMBINATIONS_WITH_TIME.set(combinations_with_time).unwrap();
+    /// Checks the design before anything runs and reports every violated constraint at once.
+    pub fn check_validity(&self) -> Result<(), ConfigErrors> {
+        let mut errors = Vec::new();
+
+        if self.iteration == 0 {
+            errors.push(ConfigError::NotPositive("iteration"));
+        }
+        if self.time == 0 {
+            errors.push(ConfigError::NotPositive("time"));
+        }
+        if self.m_of_bundle == 0 {
+            errors.push(ConfigError::NotPositive("m_of_bundle"));
+        }
+        if self.m_in_bundle == 0 {
+            errors.push(ConfigError::NotPositive("m_in_bundle"));
+        }
+        if self.informal_max_num < 0 {
+            errors.push(ConfigError::Negative { name: "informal_max_num", value: self.informal_max_num });
+        }
+
+        for (name, length) in [
+            ("social_dynamics", self.num_social_dynamics),
+            ("span", self.length_span),
+            ("enforcement", self.length_enforcement),
+            ("turbulence_rate", self.length_turbulence),
+            ("turnover_rate", self.length_turnover),
+        ] {
+            if length == 0 {
+                errors.push(ConfigError::EmptyAxis(name));
+            }
+        }
+        if self.turbulence_interval.len() != self.turbulence_rate.len() {
+            errors.push(ConfigError::TurbulenceLengthMismatch {
+                rate: self.turbulence_rate.len(),
+                interval: self.turbulence_interval.len(),
+            });
+        }
+        if self.turbulence_interval.contains(&0) {
+            errors.push(ConfigError::NotPositive("turbulence_interval"));
+        }
+        for name in self.social_dynamics.iter().filter(|&name| social_dynamics::find(name).is_none()) {
+            let known = SOCIAL_DYNAMICS.iter().map(|social_dynamics| social_dynamics.name()).collect();
+            errors.push(ConfigError::UnknownName { name: "social_dynamics", value: name.clone(), known });
+        }
+        if task_environment::find(&self.task_environment).is_none() {
+            let known = TASK_ENVIRONMENTS.iter().map(|kind| kind.name()).collect();
+            errors.push(ConfigError::UnknownName { name: "task_environment", value: self.task_environment.clone(), known });
+        }
+        if task_environment::find_interaction(&self.nk_interaction).is_none() {
+            let known = NK_INTERACTIONS.iter().map(|interaction| interaction.name()).collect();
+            errors.push(ConfigError::UnknownName { name: "nk_interaction", value: self.nk_interaction.clone(), known });
+        }
+        // The mismatches of a dimension and of the K it interacts with index its contribution as the bits of a u64.
+        let k_maximum = cmp::min(self.m.saturating_sub(1), u64::BITS as usize - 1);
+        if self.nk_k > k_maximum {
+            errors.push(ConfigError::TooLargeK { k: self.nk_k, maximum: k_maximum });
+        }
+        if learning_rule::find(&self.learning_rule).is_none() {
+            let known = LEARNING_RULES.iter().map(|learning_rule| learning_rule.name()).collect();
+            errors.push(ConfigError::UnknownName { name: "learning_rule", value: self.learning_rule.clone(), known });
+        }
+        if self.span.contains(&0) {
+            errors.push(ConfigError::NotPositive("span"));
+        }
+
+        let mut probabilities = vec![
+            ("p_addition", self.p_addition),
+            ("p_learning", self.p_learning),
+            ("p_socialization", self.p_socialization),
+            ("p_code_learning", self.p_code_learning),
+            ("homophily_strength", self.homophily_strength),
+            ("homophily_performance_weight", self.homophily_performance_weight),
+            ("informal_initial_prop", self.informal_initial_prop),
+            ("informal_rewiring_prop", self.informal_rewiring_prop),
+        ];
+        probabilities.extend(self.enforcement.iter().map(|&value| ("enforcement", value)));
+        probabilities.extend(self.turbulence_rate.iter().map(|&value| ("turbulence_rate", value)));
+        probabilities.extend(self.turnover_rate.iter().map(|&value| ("turnover_rate", value)));
+        for (name, value) in probabilities {
+            if !(0.0..=1.0).contains(&value) {
+                errors.push(ConfigError::NotProbability { name, value });
+            }
+        }
+
+        // The root and its first layer of subordinates must fit, and the
+        // centralization denominators vanish below four agents.
+        let minimum = cmp::max(4, self.span.iter().max().map_or(0, |&span| span + 1));
+        if self.n < minimum {
+            errors.push(ConfigError::TooFewAgents { n: self.n, minimum });
+        }
+
+        // The formal hierarchy alone takes N - 1 dyads, and the additional links take NUM_ADDITION more.
+        let free_dyads = self.n_dyad.saturating_sub(self.n.saturating_sub(1) + self.num_addition);
+        if self.informal_initial_num > free_dyads {
+            errors.push(ConfigError::TooManyInitialTies { informal_initial_num: self.informal_initial_num, free_dyads });
+        }
+
+        if self.shard.count == 0 || self.shard.index >= self.shard.count {
+            errors.push(ConfigError::InvalidShard(self.shard));
+        } else if self.shard.count > self.iteration {
+            // A shard without iterations would never write its checkpoint or its results.
+            errors.push(ConfigError::TooManyShards { count: self.shard.count, iteration: self.iteration });
+        }
+        if !self.shard.is_whole() && self.is_seed_drawn {
+            // Each shard would draw its own seed, and `merge` would then refuse the shards.
+            errors.push(ConfigError::ShardWithoutSeed);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErrors(errors))
+        }
+    }
 }
 
-pub fn check_validity(){
-    //TODO: Implement this function; M should be divisible by M_IN_BUNDLE.
-}
\ No newline at end of file
+/// A single constraint of the design that is violated.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    NotPositive(&'static str),
+    Negative { name: &'static str, value: isize },
+    EmptyAxis(&'static str),
+    TurbulenceLengthMismatch { rate: usize, interval: usize },
+    NotProbability { name: &'static str, value: f64 },
+    TooFewAgents { n: usize, minimum: usize },
+    TooManyInitialTies { informal_initial_num: usize, free_dyads: usize },
+    InvalidShard(Shard),
+    TooManyShards { count: usize, iteration: usize },
+    ShardWithoutSeed,
+    UnknownName { name: &'static str, value: String, known: Vec<&'static str> },
+    TooLargeK { k: usize, maximum: usize },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NotPositive(name) => write!(f, "`{}` must be positive", name),
+            ConfigError::Negative { name, value } => write!(f, "`{}` must not be negative (got {})", name, value),
+            ConfigError::EmptyAxis(name) => write!(f, "`{}` must have at least one value", name),
+            ConfigError::TurbulenceLengthMismatch { rate, interval } => write!(
+                f,
+                "`turbulence_rate` has {} values but `turbulence_interval` has {}",
+                rate, interval
+            ),
+            ConfigError::NotProbability { name, value } => write!(f, "`{}` must lie in [0, 1] (got {})", name, value),
+            ConfigError::TooFewAgents { n, minimum } => write!(f, "`n` = {} is too small for the hierarchy; at least {} agents are needed", n, minimum),
+            ConfigError::TooManyInitialTies { informal_initial_num, free_dyads } => write!(
+                f,
+                "{} initial informal ties are requested but at most {} dyads are free of formal ties",
+                informal_initial_num, free_dyads
+            ),
+            ConfigError::InvalidShard(Shard { index, count }) => write!(
+                f,
+                "`shard` must have 0 <= index < count (got index {} and count {})",
+                index, count
+            ),
+            ConfigError::TooManyShards { count, iteration } => write!(
+                f,
+                "{} shards are requested but there are only {} iterations to share between them",
+                count, iteration
+            ),
+            ConfigError::ShardWithoutSeed => write!(f, "a `shard` of several needs a `seed` given by the design, shared by every shard"),
+            ConfigError::UnknownName { name, value, known } => write!(
+                f,
+                "`{}` cannot be {:?}; the known values are {}",
+                name,
+                value,
+                known.join(", ")
+            ),
+            ConfigError::TooLargeK { k, maximum } => write!(f, "`nk_k` = {} is too large; at most {} is allowed here", k, maximum),
+        }
+    }
+}
+
+/// Every violated constraint of a design, as returned by `ExperimentConfig::check_validity`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "The experiment design is invalid:")?;
+        for error in &self.0 {
+            writeln!(f, "\t- {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(overrides: &[&str]) -> ExperimentConfig {
+        let overrides: Vec<String> = overrides.iter().map(|assignment| assignment.to_string()).collect();
+        ExperimentConfig::load(None, &overrides).unwrap()
+    }
+
+    #[test]
+    fn the_default_design_is_valid() {
+        assert_eq!(load(&[]).check_validity(), Ok(()));
+    }
+
+    #[test]
+    fn every_violation_is_reported() {
+        let errors = load(&["n=3", "social_dynamics=[\"gossip\"]", "p_learning=1.5", "span=[]"]).check_validity().unwrap_err().0;
+        let known = SOCIAL_DYNAMICS.iter().map(|social_dynamics| social_dynamics.name()).collect();
+        assert_eq!(errors, vec![
+            ConfigError::EmptyAxis("span"),
+            ConfigError::UnknownName { name: "social_dynamics", value: "gossip".to_string(), known },
+            ConfigError::NotProbability { name: "p_learning", value: 1.5 },
+            ConfigError::TooFewAgents { n: 3, minimum: 4 },
+            ConfigError::TooManyInitialTies { informal_initial_num: 7, free_dyads: 1 },
+        ]);
+    }
+
+    #[test]
+    fn a_printed_seed_loads_back() {
+        let dir = std::env::temp_dir().join(format!("flat-seed-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let seeds: Vec<u64> = (0..100).map(|_| ExperimentConfig::default().seed).chain([0, i64::MAX as u64]).collect();
+        for seed in seeds {
+            let overridden = ExperimentConfig::load(None, &[format!("seed={}", seed)]).unwrap();
+            assert_eq!(overridden.seed, seed);
+            let path = dir.join("design.toml");
+            fs::write(&path, format!("seed = {}\n", seed)).unwrap();
+            assert_eq!(ExperimentConfig::from_file(&path).unwrap().seed, seed);
+            let path = dir.join("design.json");
+            fs::write(&path, format!("{{\"seed\": {}}}", seed)).unwrap();
+            assert_eq!(ExperimentConfig::from_file(&path).unwrap().seed, seed);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shards_need_a_given_seed() {
+        let shard = |overrides: &[&str]| {
+            let mut config = load(overrides);
+            config.shard = Shard { index: 0, count: 2 };
+            config.check_validity()
+        };
+        assert_eq!(shard(&[]).unwrap_err().0, vec![ConfigError::ShardWithoutSeed]);
+        assert!(shard(&["seed=5"]).is_ok());
+    }
+}
@@ -1,26 +1,38 @@
-use rand::seq::SliceRandom;
-use rand::{rng, Rng};
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
 use std::fs::File;
 use std::io::{BufWriter, Write};
-use std::{cmp, usize};
+use std::cmp;
 use std::time::{SystemTime, UNIX_EPOCH};
-use crate::params;
+use std::sync::Arc;
+use crate::params::{ExperimentConfig, LAYER_COMBINED, LAYER_FORMAL, LAYER_INFORMAL};
+use crate::adjacency::AdjacencyMatrix;
+use crate::bit_vector::BitVector;
+use crate::learning_rule::LearningRule;
 use crate::network_analyzer::{self, NetworkAnalyzer};
+use crate::social_dynamics::SocialDynamics;
+use crate::task_environment::TaskEnvironment;
+use crate::weighted_sampler::WeightedSampler;
+
+/// Generator each scenario draws from; seeded per (iteration, combination, arm) by `ExperimentConfig::rng_for`.
+pub type ScenarioRng = ChaCha8Rng;
 
 // --------------------------------------------------------------------
 // The Scenario struct in Rust
 // --------------------------------------------------------------------
 pub struct Scenario {
+    pub config: Arc<ExperimentConfig>,
+
     // Random generator
     pub is_stale: bool,
-    pub rng: rand::rngs::ThreadRng,
+    pub rng: ScenarioRng,
     pub tic: usize,
 
-    pub social_dynamics: usize,
+    pub social_dynamics: &'static dyn SocialDynamics,
     pub is_rewiring: bool,
     pub is_random_rewiring: bool,
-    pub is_network_closure: bool,
-    pub is_preferential_attachment: bool,
+    pub learning_rule: &'static dyn LearningRule,
 
     pub span: usize,            // Span of control
     pub enforcement: f64,       // E
@@ -29,38 +41,36 @@ pub struct Scenario {
     pub turbulence_rate: f64,
     pub turnover_rate: f64,
 
-    // Reality replaced by a 1D bool array
-    pub reality: Vec<Vec<bool>>,
+    // Reality and beliefs as bit vectors over the M dimensions, dimension `bundle * M_IN_BUNDLE + element`
+    pub reality: BitVector,
+    pub task_environment: Arc<dyn TaskEnvironment>, // shared with the clones, which see the same landscape
 
-    pub belief_of: Vec<Vec<Vec<bool>>>,
-    pub performance_of: Vec<usize>,
+    pub belief_of: Vec<BitVector>,
+    pub organizational_code: Option<BitVector>, // only kept under a learning rule that uses one
+    pub performance_of: Vec<f64>,
     pub level_of: Vec<usize>,
+    pub formal_unit_of: Vec<usize>, // the subordinate of the top whose subtree holds the agent; the top is its own unit.
     pub level_range: f64,
 
-    // Networks replaced by 2D bool arrays
-    pub network: Vec<Vec<bool>>,
-    pub network_formal: Vec<Vec<bool>>,
-    pub network_informal: Vec<Vec<bool>>,
-    pub network_limited: Vec<Vec<bool>>,
+    // Networks as packed adjacency matrices
+    pub network: AdjacencyMatrix,
+    pub network_formal: AdjacencyMatrix,
+    pub network_informal: AdjacencyMatrix,
+    pub network_limited: AdjacencyMatrix,
     pub network_analyzer: crate::network_analyzer::NetworkAnalyzer,
+    pub network_analyzer_formal: Option<NetworkAnalyzer>, // only kept when `layered_metrics` is set
+    pub network_analyzer_informal: Option<NetworkAnalyzer>,
 
     pub degree: Vec<isize>,
     pub degree_formal: Vec<isize>,
     pub degree_informal: Vec<isize>,
 
-    pub preference_score: Vec<Vec<f64>>,
-    pub preference_score_avg: Vec<f64>,
-
     pub performance_avg: f64,
+    pub performance_code: f64, // of the organizational code, 0 without one
 
-    pub average_path_length: f64,
-    pub network_efficiency: f64,
-    pub global_clustering_watts_strogatz: f64,
-    pub closeness_centralization: f64,
-    pub triadic_centralization: f64,
-    pub shortest_path_variance: f64,
-    pub sigma: f64,
-    pub omega: f64,
+    // Outcomes across the layers; the network metrics are read from the analyzers
+    pub informal_within_unit: f64,
+    pub multiplex_participation: f64,
 
     //Utility 
     pub iterator_focal_index: Vec<usize>,
@@ -70,7 +80,25 @@ pub struct Scenario {
 
 impl Scenario {
     pub fn new(
-        social_dynamics: usize,
+        config: &Arc<ExperimentConfig>,
+        mut rng: ScenarioRng,
+        social_dynamics: &'static dyn SocialDynamics,
+        span: usize,
+        enforcement: f64,
+        turbulence_rate: f64,
+        turnover_rate: f64,
+    ) -> Self {
+        let task_environment = config.get_task_environment().build(config, &mut rng);
+        Self::with_task_environment(config, rng, task_environment, social_dynamics, span, enforcement, turbulence_rate, turnover_rate)
+    }
+
+    /// A scenario in a task environment built beforehand, as the clones share the one of their original.
+    #[allow(clippy::too_many_arguments)]
+    fn with_task_environment(
+        config: &Arc<ExperimentConfig>,
+        rng: ScenarioRng,
+        task_environment: Arc<dyn TaskEnvironment>,
+        social_dynamics: &'static dyn SocialDynamics,
         span: usize,
         enforcement: f64,
         turbulence_rate: f64,
@@ -78,80 +106,70 @@ impl Scenario {
     ) -> Self {
         let is_stale = false;
         let tic =  SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as usize;
-        let rng = rng();
-        let reality = vec![vec![false; params::M_IN_BUNDLE]; params::M_OF_BUNDLE];
-        let belief_of = vec![vec![vec![false; params::M_IN_BUNDLE]; params::M_OF_BUNDLE]; params::N];
-        let performance_usize = vec![0; params::N];
-        let level_of = vec![0; params::N];
-        let network = vec![vec![false; params::N]; params::N];
+        let reality = BitVector::new(config.m);
+        let belief_of = vec![BitVector::new(config.m); config.n];
+        let performance_of = vec![0.0; config.n];
+        let level_of = vec![0; config.n];
+        let network = AdjacencyMatrix::new(config.n);
         let network_formal = network.clone();
         let network_informal = network.clone();
         let network_limited = network.clone();
-        let network_analyzer = NetworkAnalyzer::new();
-        let degree = vec![0;params::N];
+        let network_analyzer = NetworkAnalyzer::new(config, &rng, LAYER_COMBINED);
+        let network_analyzer_formal = new_layer_analyzer(config, &rng, LAYER_FORMAL);
+        let network_analyzer_informal = new_layer_analyzer(config, &rng, LAYER_INFORMAL);
+        let degree = vec![0;config.n];
         let degree_formal = degree.clone();
         let degree_informal = degree.clone();
-        let preference_score = vec![vec![0.0; params::N]; params::N];
-        let preference_score_avg = vec![0.0; params::N];
-        let iterator_focal_index: Vec<usize> = (0..params::N).collect();
+        let iterator_focal_index: Vec<usize> = (0..config.n).collect();
         let iterator_target_index = iterator_focal_index.clone();
-        let mut iterator_dyad = Vec::with_capacity(params::N_DYAD);
+        let mut iterator_dyad = Vec::with_capacity(config.n_dyad);
 
-        for i in 0..params::N {
-            for j in (i + 1)..params::N {
+        for i in 0..config.n {
+            for j in (i + 1)..config.n {
                 iterator_dyad.push((i, j));
             }
         }
 
         let mut scenario = Scenario{
+            config: Arc::clone(config),
             is_stale,
             rng,
             tic,
             social_dynamics,
             is_rewiring: true,
             is_random_rewiring: false,
-            is_network_closure: false,
-            is_preferential_attachment: false,
+            learning_rule: config.get_learning_rule(),
             span,
             enforcement,
             turbulence_rate,
             turnover_rate,
             reality,
+            task_environment,
             belief_of,
-            performance_of: performance_usize,
+            organizational_code: None,
+            performance_of,
             level_of,
+            formal_unit_of: vec![0; config.n],
             level_range: 0.0,
             network,
             network_formal,
             network_informal,
             network_limited,
             network_analyzer,
+            network_analyzer_formal,
+            network_analyzer_informal,
             degree,
             degree_formal,
             degree_informal,
-            preference_score,
-            preference_score_avg,
             performance_avg: 0.0,
-            average_path_length: 0.0,
-            network_efficiency: 0.0,
-            global_clustering_watts_strogatz: 0.0,
-            closeness_centralization: 0.0,
-            triadic_centralization: 0.0,
-            shortest_path_variance: 0.0,
+            performance_code: 0.0,
             iterator_focal_index,
             iterator_target_index,
             iterator_dyad,
-            sigma: 0.0,
-            omega: 0.0,
+            informal_within_unit: 0.0,
+            multiplex_participation: 0.0,
         };
 
-        // Set flags based on social_dynamics
-        match social_dynamics {
-            0 => scenario.is_network_closure = true,
-            1 => scenario.is_preferential_attachment = true,
-            _ => {}
-        }
-
         // Initialize everything
         scenario.initialize();
 
@@ -159,10 +177,14 @@ impl Scenario {
     }
 
     /// Equivalent to Java's public Scenario getClone().
-    /// Returns a "clone" of the current Scenario with all relevant fields copied.
-    pub fn get_clone(&self) -> Scenario {
-        // Create the new scenario using the same constructor arguments.
-        let mut clone = Scenario::new(
+    /// Returns a "clone" of the current Scenario with all relevant fields copied;
+    /// the clone continues on its own generator `rng`.
+    pub fn get_clone(&self, rng: ScenarioRng) -> Scenario {
+        // Create the new scenario using the same constructor arguments, in the same task environment.
+        let mut clone = Scenario::with_task_environment(
+            &self.config,
+            rng,
+            Arc::clone(&self.task_environment),
             self.social_dynamics,
             self.span,
             self.enforcement,
@@ -174,10 +196,13 @@ impl Scenario {
         clone.performance_of = self.performance_of.clone();
         clone.performance_avg = self.performance_avg;
         clone.belief_of = self.belief_of.clone();
+        clone.organizational_code = self.organizational_code.clone();
         clone.network_formal = self.network_formal.clone();
         clone.network_informal = self.network_informal.clone();
         clone.network = self.network.clone();
-        clone.network_analyzer = NetworkAnalyzer::new();
+        clone.network_analyzer = NetworkAnalyzer::new(&self.config, &clone.rng, LAYER_COMBINED);
+        clone.network_analyzer_formal = new_layer_analyzer(&self.config, &clone.rng, LAYER_FORMAL);
+        clone.network_analyzer_informal = new_layer_analyzer(&self.config, &clone.rng, LAYER_INFORMAL);
         clone.degree = self.degree.clone();
         clone.degree_formal = self.degree_formal.clone();
         clone.degree_informal = self.degree_informal.clone();
@@ -188,8 +213,8 @@ impl Scenario {
     }
 
     /// Equivalent to Java's public Scenario getClone(boolean, boolean).
-    pub fn get_clone_with_params(&self, is_rewiring: bool, is_random_rewiring: bool) -> Scenario {
-        let mut clone = self.get_clone();
+    pub fn get_clone_with_params(&self, rng: ScenarioRng, is_rewiring: bool, is_random_rewiring: bool) -> Scenario {
+        let mut clone = self.get_clone(rng);
         clone.set_network_params(is_rewiring, is_random_rewiring);
         clone
     }
@@ -210,20 +235,20 @@ impl Scenario {
     /// Equivalent to private void initializeNetwork().
     fn initialize_network(&mut self) {
         // Re-initialize them:
-        self.network = vec![vec![false; params::N]; params::N];
-        self.network_formal = vec![vec![false; params::N]; params::N];
-        self.network_informal = vec![vec![false; params::N]; params::N];
-        self.network_limited = vec![vec![false; params::N]; params::N];
-        self.level_of = vec![0; params::N];
-        self.degree = vec![0; params::N];
-        self.degree_formal = vec![0; params::N];
-        self.degree_informal = vec![0; params::N];
+        self.network = AdjacencyMatrix::new(self.config.n);
+        self.network_formal = AdjacencyMatrix::new(self.config.n);
+        self.network_informal = AdjacencyMatrix::new(self.config.n);
+        self.network_limited = AdjacencyMatrix::new(self.config.n);
+        self.level_of = vec![0; self.config.n];
+        self.degree = vec![0; self.config.n];
+        self.degree_formal = vec![0; self.config.n];
+        self.degree_informal = vec![0; self.config.n];
 
         let mut level_now = 1;
         let mut upper_start = 0;
         let mut upper_end = 1;
         let mut lower_start = upper_end;
-        let mut lower_end = lower_start + self.span as usize;
+        let mut lower_end = lower_start + self.span;
         self.level_of[0] = 1;
 
         // Build the hierarchical network
@@ -231,14 +256,13 @@ impl Scenario {
             level_now += 1;
             for upper in upper_start..upper_end {
                 for lower in lower_start..lower_end {
-                    self.network[upper][lower] = true;
-                    self.network[lower][upper] = true;
+                    self.network.set_tie(upper, lower, true);
                     self.degree[upper] += 1;
                     self.degree[lower] += 1;
                     self.level_of[lower] = level_now;
 
                 }
-                if params::LINK_LEVEL {
+                if self.config.link_level {
                     let lower_num = lower_end - lower_start;
                     if lower_num == 0 {
                         continue;
@@ -250,37 +274,40 @@ impl Scenario {
                         if focal == target {
                             break;
                         }
-                        self.network[focal][target] = true;
-                        self.network[target][focal] = true;
+                        self.network.set_tie(focal, target, true);
                         self.degree[focal] += 1;
                         self.degree[target] += 1;
                     }
                 }
                 lower_start = lower_end;
-                lower_end = cmp::min(lower_start + self.span, params::N);
+                lower_end = cmp::min(lower_start + self.span, self.config.n);
             }
-            if lower_start == params::N {
+            if lower_start == self.config.n {
                 break;
             }
             upper_start = upper_end;
-            upper_end = upper_start + self.span.pow((level_now - 1) as u32) as usize;
+            upper_end = upper_start + self.span.pow((level_now - 1) as u32);
         }
         self.level_range = (level_now - self.level_of[0]) as f64;
 
+        // The formal units: in breadth-first order, the superior of agent i is (i - 1) / span.
+        self.formal_unit_of = vec![0; self.config.n];
+        for focal in 1..self.config.n {
+            self.formal_unit_of[focal] = if self.level_of[focal] == 2 { focal } else { self.formal_unit_of[(focal - 1) / self.span] };
+        }
+
         // Tie enforcement
-        for focal in 0..params::N {
-            for target in focal..params::N {
-                if self.network[focal][target] {
+        for focal in 0..self.config.n {
+            for target in focal..self.config.n {
+                if self.network.get(focal, target) {
                     if self.rng.random::<f64>() < self.enforcement {
                         // Enforced
-                        self.network_formal[focal][target] = true;
-                        self.network_formal[target][focal] = true;
+                        self.network_formal.set_tie(focal, target, true);
                         self.degree_formal[focal] += 1;
                         self.degree_formal[target] += 1;
                     } else {
                         // Flexible
-                        self.network_informal[focal][target] = true;
-                        self.network_informal[target][focal] = true;
+                        self.network_informal.set_tie(focal, target, true);
                         self.degree_informal[focal] += 1;
                         self.degree_informal[target] += 1;
                     }
@@ -289,26 +316,24 @@ impl Scenario {
         }
 
         // Additional links
-        let mut num_addition_left:usize = params::NUM_ADDITION;
+        let mut num_addition_left:usize = self.config.num_addition;
         if num_addition_left > 0 {
-            self.iterator_dyad.shuffle(&mut rng());
+            self.iterator_dyad.shuffle(&mut self.rng);
             'outer: loop {
+                let num_addition_before = num_addition_left;
                 for &(focal, target) in &self.iterator_dyad {
-                    if !self.network[focal][target]
-                        && (self.degree_informal[focal] < params::INFORMAL_MAX_NUM
-                            || self.degree_informal[target] < params::INFORMAL_MAX_NUM)
+                    if !self.network.get(focal, target)
+                        && (self.degree_informal[focal] < self.config.informal_max_num
+                            || self.degree_informal[target] < self.config.informal_max_num)
                         && num_addition_left > 0
                     {
-                        self.network[focal][target] = true;
-                        self.network[target][focal] = true;
+                        self.network.set_tie(focal, target, true);
                         if self.rng.random::<f64>() < self.enforcement {
                             // Enforced
-                            self.network_formal[focal][target] = true;
-                            self.network_formal[target][focal] = true;
+                            self.network_formal.set_tie(focal, target, true);
                         } else {
                             // Flexible
-                            self.network_informal[focal][target] = true;
-                            self.network_informal[target][focal] = true;
+                            self.network_informal.set_tie(focal, target, true);
                         }
                         num_addition_left -= 1;
                         if num_addition_left == 0 {
@@ -316,18 +341,19 @@ impl Scenario {
                         }
                     }
                 }
-                if num_addition_left <= 0 {
+                // Another pass cannot add what this one could not.
+                if num_addition_left == num_addition_before {
                     break 'outer;
                 }
             }
         }
 
-        if params::LIMIT_LEVEL {
+        if self.config.limit_level {
             for &focal in self.iterator_focal_index.iter() {
-                self.network_limited[focal][focal] = true;
-                for target in focal..params::N {
+                self.network_limited.set(focal, focal, true);
+                for target in focal..self.config.n {
                     if (self.level_of[focal] as i32 - self.level_of[target] as i32).abs() > 1 {
-                        self.network_limited[focal][focal] = true;
+                        self.network_limited.set(focal, focal, true);
                     }
                 }
             }
@@ -336,25 +362,38 @@ impl Scenario {
         // println!("\n\ns{} {} <- {}", self.span, self.network.iter().flatten().map(|&x| x as usize).sum::<usize>(), format!("{:?}",self.network));
         // println!("\n\nINFORMAL\ts{} {} <- {}", self.span, self.network_informal.iter().flatten().map(|&x| x as usize).sum::<usize>(), format!("{:?}",self.network_informal));
 
-        self.network_analyzer = network_analyzer::NetworkAnalyzer::new();
+        self.network_analyzer = network_analyzer::NetworkAnalyzer::new(&self.config, &self.rng, LAYER_COMBINED);
+        self.network_analyzer_formal = new_layer_analyzer(&self.config, &self.rng, LAYER_FORMAL);
+        self.network_analyzer_informal = new_layer_analyzer(&self.config, &self.rng, LAYER_INFORMAL);
 
         
     }
 
     fn initialize_entity(&mut self) {
-        for bundle in 0..params::M_OF_BUNDLE {
-            for element in 0..params::M_IN_BUNDLE{
-                self.reality[bundle][element] = self.rng.random::<bool>();
-                for focal in 0..params::N {
-                    self.belief_of[focal][bundle][element] = self.rng.random::<bool>();
+        for bundle in 0..self.config.m_of_bundle {
+            for element in 0..self.config.m_in_bundle{
+                let dimension = bundle * self.config.m_in_bundle + element;
+                let value = self.rng.random::<bool>();
+                self.reality.set(dimension, value);
+                for focal in 0..self.config.n {
+                    let value = self.rng.random::<bool>();
+                    self.belief_of[focal].set(dimension, value);
                 }
             }
         }
+        if self.learning_rule.uses_code() {
+            let mut code = BitVector::new(self.config.m);
+            for dimension in 0..self.config.m {
+                let value = self.rng.random::<bool>();
+                code.set(dimension, value);
+            }
+            self.organizational_code = Some(code);
+        }
     }
 
     fn initialize_outcome(&mut self) {
-        for n in 0..params::N {
-            self.performance_of[n] = 0;
+        for n in 0..self.config.n {
+            self.performance_of[n] = 0.0;
         }
         self.set_performance();
         self.set_outcome();
@@ -363,12 +402,12 @@ impl Scenario {
     pub fn step_forward(&mut self){
         if self.is_rewiring{
             if self.is_random_rewiring{
-                self.do_random_rewiring(params::INFORMAL_REWIRING_NUM, params::INFORMAL_REWIRING_NUM);
+                self.do_random_rewiring(self.config.informal_rewiring_num, self.config.informal_rewiring_num);
             }else{
-                self.do_rewiring(params::INFORMAL_REWIRING_NUM, params::INFORMAL_REWIRING_NUM);
+                self.do_rewiring(self.config.informal_rewiring_num, self.config.informal_rewiring_num);
             }
         }
-        if !self.is_stale{
+        if !self.is_stale || !self.learning_rule.stops_when_even() {
             self.do_learning();
         }
         self.set_outcome();
@@ -380,115 +419,71 @@ impl Scenario {
     pub fn set_outcome(&mut self) {
         self.is_stale = true;
         let performance_of_first = self.performance_of[0];
-        self.performance_avg = performance_of_first as f64;
-        for focal in 1..params::N {
+        self.performance_avg = performance_of_first;
+        for focal in 1..self.config.n {
             if self.performance_of[focal] != performance_of_first { 
                 self.is_stale = false;
             }
-            self.performance_avg += self.performance_of[focal] as f64;
+            self.performance_avg += self.performance_of[focal];
         }
-        self.performance_avg /= params::M_N as f64;
+        self.performance_avg /= self.config.m_n as f64;
+        self.performance_code = match &self.organizational_code {
+            Some(code) => self.get_performance_of_beliefs(code) / self.config.m as f64,
+            None => 0.0,
+        };
         self.network_analyzer.set_network_metrics(&self.network);
-        self.average_path_length = self.network_analyzer.get_average_path_length();
-        self.network_efficiency = self.network_analyzer.get_network_efficiency();
-        self.global_clustering_watts_strogatz = self.network_analyzer.get_global_clustering_watts_strogatz();
-        self.closeness_centralization = self.network_analyzer.get_closeness_centralization();
-        self.triadic_centralization = self.network_analyzer.get_triadic_centralization();
-        self.shortest_path_variance = self.network_analyzer.get_shortest_path_variance();
-        self.omega = self.network_analyzer.get_omega();
-        self.sigma = self.network_analyzer.get_sigma();
-    }
-
-    pub fn set_preference_score(&mut self) {
-        self.preference_score = vec![vec![0.0; params::N]; params::N];
-        self.preference_score_avg = vec![0.0; params::N];
-
-        if self.is_network_closure {
-            self.set_preference_score_raw_network_closure();
-        } else if self.is_preferential_attachment {
-            self.set_preference_score_raw_preferential_attachment();
-        }
-    }
-
-    fn set_preference_score_raw_network_closure(&mut self) {
-        for &focal in self.iterator_focal_index.iter() {
-            for target in focal..params::N {
-                let mut score = 0.0;
-                for i in 0..params::N {
-                    if self.network[i][focal] && self.network[i][target] {
-                        score += 1.0;
-                    }
-                }
-                self.preference_score[focal][target] = score;
-                self.preference_score[target][focal] = score;
-            }
-        }
-        for &focal in self.iterator_focal_index.iter() {
-            self.preference_score[focal][focal] = 0.0;
-            for &target in self.iterator_target_index.iter() {
-                let denom = if self.network[focal][target] {
-                    self.degree[focal]
-                } else {
-                    self.degree[focal] + 1
-                } as f64;
-                self.preference_score[focal][target] /= denom;
-                self.preference_score_avg[focal] += self.preference_score[focal][target];
-            }
-            // Avoid dividing by zero if degree == 0
-            if self.degree[focal] > 0 {
-                self.preference_score_avg[focal] /= self.degree[focal] as f64;
-            }
-        }
-    }
+        self.network_analyzer.set_community_metrics(&self.network, &self.formal_unit_of);
+        self.network_analyzer.set_degree_metrics(&self.network, &self.level_of);
 
-    fn set_preference_score_raw_preferential_attachment(&mut self) {
-        for &focal in self.iterator_focal_index.iter() {
-            for target in focal..params::N {
-                let d_target = self.degree[target] as f64;
-                let d_focal = self.degree[focal] as f64;
-                self.preference_score[focal][target] = d_target;
-                self.preference_score[target][focal] = d_focal;
-            }
-        }
-        for &focal in self.iterator_focal_index.iter() {
-            self.preference_score[focal][focal] = 0.0;
-            for &target in self.iterator_target_index.iter() {
-                self.preference_score_avg[focal] += self.preference_score[focal][target];
-            }
-            if self.degree[focal] > 0 {
-                self.preference_score_avg[focal] /= self.degree[focal] as f64;
+        for (analyzer, network) in [(&mut self.network_analyzer_formal, &self.network_formal), (&mut self.network_analyzer_informal, &self.network_informal)] {
+            if let Some(analyzer) = analyzer {
+                analyzer.set_network_metrics(network);
+                analyzer.set_community_metrics(network, &self.formal_unit_of);
+                analyzer.set_degree_metrics(network, &self.level_of);
             }
         }
+        self.set_cross_layer_outcome();
     }
 
-    /// Equivalent to double getRewiringWeight(int focal, int target).
-    fn get_rewiring_weight(&self, focal: usize, target: usize) -> f64 {
-        if self.is_network_closure {
-            self.get_rewiring_weight_network_closure(focal, target)
-        } else if self.is_preferential_attachment {
-            self.get_rewiring_weight_preferential_attachment(focal, target)
-        } else {
-            0.0
-        }
+    /// The analyzer holding the metrics of a layer (`LAYER_COMBINED`, `LAYER_FORMAL` or `LAYER_INFORMAL`);
+    /// the formal and informal layers only have one under `layered_metrics`.
+    pub fn analyzer_of(&self, layer: usize) -> &NetworkAnalyzer {
+        let analyzer = match layer {
+            LAYER_FORMAL => &self.network_analyzer_formal,
+            LAYER_INFORMAL => &self.network_analyzer_informal,
+            _ => return &self.network_analyzer,
+        };
+        analyzer.as_ref().expect("the layers are only analyzed under `layered_metrics`")
     }
 
-    /// Equivalent to double getRewiringWeightNetworkClosure(int focal, int target).
-    fn get_rewiring_weight_network_closure(&self, focal: usize, target: usize) -> f64 {
-        let mut preference_score = 1.0; // to avoid weight of 0
-        for i in 0..params::N {
-            if self.network[focal][i] && self.network[target][i] {
-                preference_score += 1.0;
+    /// The share of the informal ties that stay within a formal unit, and the average
+    /// multiplex participation coefficient over the agents with any tie,
+    /// P_i = 2 (1 - (k_formal / k)^2 - (k_informal / k)^2) for the two layers.
+    /// The layers never share a tie, so how the informal ties sit in the formal units is what relates them.
+    fn set_cross_layer_outcome(&mut self) {
+        // Every tie is counted from both of its ends, which cancels in the ratio.
+        let mut within = 0;
+        let mut all = 0;
+        for focal in 0..self.config.n {
+            for target in self.network_informal.neighbors(focal) {
+                within += (self.formal_unit_of[focal] == self.formal_unit_of[target]) as usize;
+                all += 1;
             }
         }
-        let denom = cmp::max(self.degree[focal], self.degree[target]) as f64
-            + if self.network[focal][target] { 0.0 } else { 1.0 };
-        preference_score / denom
-    }
+        self.informal_within_unit = if all == 0 { 0.0 } else { within as f64 / all as f64 };
 
-    fn get_rewiring_weight_preferential_attachment(&self, focal: usize, target: usize) -> f64 {
-        let df = self.degree[focal] as f64;
-        let dt = self.degree[target] as f64;
-        df.min(dt)
+        let mut participation_sum = 0.0;
+        let mut num_tied = 0;
+        for focal in 0..self.config.n {
+            let degree = (self.degree_formal[focal] + self.degree_informal[focal]) as f64;
+            if degree > 0.0 {
+                let share_formal = self.degree_formal[focal] as f64 / degree;
+                let share_informal = self.degree_informal[focal] as f64 / degree;
+                participation_sum += 2.0 * (1.0 - share_formal.powi(2) - share_informal.powi(2));
+                num_tied += 1;
+            }
+        }
+        self.multiplex_participation = if num_tied == 0 { 0.0 } else { participation_sum / num_tied as f64 };
     }
 
     pub fn do_rewiring(&mut self, num_formation: usize, num_break: usize) {
@@ -500,129 +495,148 @@ impl Scenario {
         }
     }
 
+    /// Weight of a dyad in tie break: the break weight of an informal tie, 0 otherwise.
+    fn get_tie_break_weight(&self, focal: usize, target: usize) -> f64 {
+        if self.network_informal.get(focal, target) { self.social_dynamics.break_weight(self, focal, target) } else { 0.0 }
+    }
+
+    /// Weight of a dyad in tie formation: the formation weight of an untied dyad open to a new informal tie, 0 otherwise.
+    fn get_tie_formation_weight(&self, focal: usize, target: usize) -> f64 {
+        if !self.network.get(focal, target)
+            && focal != target
+            && !self.network_limited.get(focal, target)
+            && self.degree_informal[focal] < self.config.informal_max_num
+            && self.degree_informal[target] < self.config.informal_max_num
+        {
+            self.social_dynamics.formation_weight(self, focal, target)
+        } else {
+            0.0
+        }
+    }
+
+    /// Breaks informal ties one at a time, each with probability proportional to its shortfall from the
+    /// strongest informal tie; when all are equally strong, a tie is drawn uniformly instead.
     fn do_tie_break(&mut self, mut num_break: usize) {
-        let mut probability = vec![0.0; params::N_DYAD];
+        let (ties, mut sampler) = self.get_tie_break_sampler();
         while num_break > 0 {
-            probability.fill(0.0);
-            let mut dyad2_cut_weight_max = f64::MIN;
-            // Calculate rewiring weights
-            for (d, (focal, target)) in self.iterator_dyad.iter().enumerate() {
-                if self.network_informal[*focal][*target] {
-                    let w = self.get_rewiring_weight(*focal, *target);
-                    probability[d] = w;
-                    if w > dyad2_cut_weight_max {
-                        dyad2_cut_weight_max = w;
-                    }
-                }
-            }
-            // Convert to "largest becomes zero" style
-            let mut probability_denominator = 0.0;
-            for prob in &mut probability {
-                if *prob != 0.0 {
-                    *prob = dyad2_cut_weight_max - *prob;
-                    probability_denominator += *prob;
-                }
+            let Some((focal, target)) = self.draw_tie_break(&ties, &sampler) else { break };
+            self.set_informal_tie(focal, target, false);
+            self.update_tie_break_weights(&ties, &mut sampler, focal, target);
+            num_break -= 1;
+        }
+    }
+
+    /// The informal ties and a sampler over their tie-break weights.
+    fn get_tie_break_sampler(&self) -> (InformalTies, WeightedSampler) {
+        let ties = InformalTies::new(&self.network_informal);
+        let sampler = WeightedSampler::new(ties.dyads.iter().map(|&(focal, target)| self.get_tie_break_weight(focal, target)).collect());
+        (ties, sampler)
+    }
+
+    /// A tie drawn by shortfall, or uniformly from the ties not yet broken; `None` once all are broken.
+    fn draw_tie_break(&mut self, ties: &InformalTies, sampler: &WeightedSampler) -> Option<(usize, usize)> {
+        match sampler.sample_shortfall(&mut self.rng) {
+            Some(item) => Some(ties.dyads[item]),
+            None => {
+                let left: Vec<(usize, usize)> = ties.dyads.iter().copied().filter(|&(focal, target)| self.network_informal.get(focal, target)).collect();
+                left.choose(&mut self.rng).copied()
             }
-            if probability_denominator == 0.0 {
-                probability.fill(1.0);
-                probability_denominator = 1.0;
-                self.iterator_dyad.shuffle(&mut self.rng);
-            }
-            // Choose
-            let marker = self.rng.random::<f64>();
-            let mut probability_cum = 0.0;
-            for (d, (focal, target)) in self.iterator_dyad.iter().enumerate() {
-                if probability[d] != 0.0 {
-                    probability_cum += probability[d] / probability_denominator;
-                    if probability_cum >= marker {
-                        self.network[*focal][*target] = false;
-                        self.network[*target][*focal] = false;
-                        self.network_informal[*focal][*target] = false;
-                        self.network_informal[*target][*focal] = false;
-                        self.degree[*focal] -= 1;
-                        self.degree_informal[*focal] -= 1;
-                        self.degree[*target] -= 1;
-                        self.degree_informal[*target] -= 1;
-                        num_break -= 1;
-                        break;
-                    }
-                }
+        }
+    }
+
+    /// Only the ties with an end at either agent of a changed dyad change weight.
+    fn update_tie_break_weights(&self, ties: &InformalTies, sampler: &mut WeightedSampler, focal: usize, target: usize) {
+        for end in [focal, target] {
+            for &item in &ties.items_of[end] {
+                let (end_a, end_b) = ties.dyads[item];
+                sampler.set(item, self.get_tie_break_weight(end_a, end_b));
             }
         }
     }
 
     /// Equivalent to void doTieFormation(int numFormation).
+    /// Forms informal ties one at a time, each with probability proportional to its rewiring weight;
+    /// when no dyad has weight, an untied dyad outside the formal reach is drawn uniformly instead.
     fn do_tie_formation(&mut self, mut num_formation: usize) {
+        let mut sampler = self.get_tie_formation_sampler();
         while num_formation > 0 {
-            let mut probability = vec![0.0; params::N_DYAD];
-            let mut probability_denominator = 0.0;
-            // Collect probabilities
-            for (d, (focal, target)) in self.iterator_dyad.iter().enumerate() {
-                if !self.network[*focal][*target]
-                    && *focal != *target
-                    && !self.network_limited[*focal][*target]
-                    && self.degree_informal[*focal] < params::INFORMAL_MAX_NUM
-                    && self.degree_informal[*target] < params::INFORMAL_MAX_NUM
-                {
-                    probability[d] = self.get_rewiring_weight(*focal, *target);
-                    probability_denominator += probability[d];
-                }
+            let Some((focal, target)) = self.draw_tie_formation(&sampler) else { break };
+            self.set_informal_tie(focal, target, true);
+            self.update_tie_formation_weights(&mut sampler, focal, target);
+            num_formation -= 1;
+        }
+    }
+
+    /// A sampler over the tie-formation weights of every dyad, in the order of `dyad_index`.
+    fn get_tie_formation_sampler(&self) -> WeightedSampler {
+        let n = self.config.n;
+        WeightedSampler::new(
+            (0..n)
+                .flat_map(|focal| ((focal + 1)..n).map(move |target| (focal, target)))
+                .map(|(focal, target)| self.get_tie_formation_weight(focal, target))
+                .collect(),
+        )
+    }
+
+    /// A dyad drawn by formation weight, or uniformly from the untied dyads outside `network_limited`;
+    /// `None` when there are none.
+    fn draw_tie_formation(&mut self, sampler: &WeightedSampler) -> Option<(usize, usize)> {
+        match sampler.sample(&mut self.rng) {
+            Some(item) => Some(dyad_of(self.config.n, item)),
+            None => {
+                let open: Vec<(usize, usize)> = self.iterator_dyad.iter().copied()
+                    .filter(|&(focal, target)| focal != target && !self.network.get(focal, target) && !self.network_limited.get(focal, target))
+                    .collect();
+                open.choose(&mut self.rng).copied()
             }
-            if probability_denominator == 0.0 {
-                probability.fill(1.0);
-                probability_denominator = 1.0;
-                self.iterator_dyad.shuffle(&mut self.rng);
-            }
-            let marker = self.rng.random::<f64>();
-            let mut probability_cum = 0.0;
-            for (d, (focal, target)) in self.iterator_dyad.iter().enumerate() {
-                if probability[d] != 0.0 {
-                    probability_cum += probability[d] / probability_denominator;
-                    if probability_cum >= marker {
-                        self.network[*focal][*target] = true;
-                        self.network[*target][*focal] = true;
-                        self.network_informal[*focal][*target] = true;
-                        self.network_informal[*target][*focal] = true;
-                        self.degree[*focal] += 1;
-                        self.degree_informal[*focal] += 1;
-                        self.degree[*target] += 1;
-                        self.degree_informal[*target] += 1;
-                        num_formation -= 1;
-                        break;
-                    }
-                }
+        }
+    }
+
+    /// Only the dyads with an end at either agent of a changed dyad change weight.
+    fn update_tie_formation_weights(&self, sampler: &mut WeightedSampler, focal: usize, target: usize) {
+        let n = self.config.n;
+        for end in [focal, target] {
+            for other in (0..n).filter(|&other| other != end) {
+                sampler.set(dyad_index(n, end, other), self.get_tie_formation_weight(end, other));
             }
         }
     }
 
+    /// Forms or breaks an informal tie, keeping the degrees in step.
+    fn set_informal_tie(&mut self, focal: usize, target: usize, is_tied: bool) {
+        self.network.set_tie(focal, target, is_tied);
+        self.network_informal.set_tie(focal, target, is_tied);
+        let change = if is_tied { 1 } else { -1 };
+        self.degree[focal] += change;
+        self.degree_informal[focal] += change;
+        self.degree[target] += change;
+        self.degree_informal[target] += change;
+    }
+
     /// Equivalent to void doRandomRewiring(int numFormation, int numBreak).
     fn do_random_rewiring(&mut self, mut num_formation: usize, mut num_break: usize) {
         let mut keep_going = true;
         while keep_going {
             self.iterator_dyad.shuffle(&mut self.rng);
             for (focal, target) in &self.iterator_dyad {
-                if self.network_informal[*focal][*target] && num_break > 0 {
+                if self.network_informal.get(*focal, *target) && num_break > 0 {
                     // Remove this informal tie
-                    self.network[*focal][*target] = false;
-                    self.network[*target][*focal] = false;
-                    self.network_informal[*focal][*target] = false;
-                    self.network_informal[*target][*focal] = false;
+                    self.network.set_tie(*focal, *target, false);
+                    self.network_informal.set_tie(*focal, *target, false);
                     self.degree[*focal] -= 1;
                     self.degree_informal[*focal] -= 1;
                     self.degree[*target] -= 1;
                     self.degree_informal[*target] -= 1;
                     num_break -= 1;
                 } else if num_formation > 0
-                    && !self.network[*focal][*target]
+                    && !self.network.get(*focal, *target)
                     && focal != target
-                    && (self.degree_informal[*focal] < params::INFORMAL_MAX_NUM
-                        || self.degree_informal[*target] < params::INFORMAL_MAX_NUM)
-                    && !self.network_limited[*focal][*target]
+                    && (self.degree_informal[*focal] < self.config.informal_max_num
+                        || self.degree_informal[*target] < self.config.informal_max_num)
+                    && !self.network_limited.get(*focal, *target)
                 {
-                    self.network[*focal][*target] = true;
-                    self.network[*target][*focal] = true;
-                    self.network_informal[*focal][*target] = true;
-                    self.network_informal[*target][*focal] = true;
+                    self.network.set_tie(*focal, *target, true);
+                    self.network_informal.set_tie(*focal, *target, true);
                     self.degree[*focal] += 1;
                     self.degree_informal[*focal] += 1;
                     self.degree[*target] += 1;
@@ -641,55 +655,30 @@ impl Scenario {
         }
     }
 
+    /// Every agent revises its beliefs under the learning rule: a belief that the rule contradicts, judged
+    /// on the beliefs before the step, is flipped with the rule's probability (P_LEARNING unless it says otherwise).
     fn do_learning(&mut self) {
-        let mut majority_opinion_count = vec![vec![vec![0; params::M_IN_BUNDLE]; params::M_OF_BUNDLE]; params::N];
-        for (focal, target) in &self.iterator_dyad {
-            let (superior, inferior) = if self.network[*focal][*target] && self.performance_of[*focal] != self.performance_of[*target] {
-                if self.performance_of[*focal] > self.performance_of[*target] {
-                    (*focal, *target)
-                } else{
-                    (*target, *focal)
-                }
-            } else {
-                continue;
-            };
-            for bundle in 0..params::M_OF_BUNDLE{
-                for element in 0..params::M_IN_BUNDLE{
-                    majority_opinion_count[inferior][bundle][element] += if self.belief_of[superior][bundle][element] { 1 } else { -1 };
-                }
-            }
-        }
-        for focal in 0..params::N {
-            for bundle in 0..params::M_OF_BUNDLE {
-                let beliefs = &mut self.belief_of[focal][bundle];
-                let counts = &majority_opinion_count[focal][bundle];
-                for element in 0..params::M_IN_BUNDLE {
-                    let count = counts[element];
-                    let belief = beliefs[element];
-                    if (count > 0 && belief) || (count < 0 && !belief) || count == 0 {
-                        continue;
-                    }
-                    if self.rng.random::<f64>() < params::P_LEARNING {
-                        beliefs[element] = !belief;
-                    }
+        let learning_rule = self.learning_rule;
+        let is_contradicted: Vec<BitVector> = (0..self.config.n).map(|focal| learning_rule.contradicted(self, focal)).collect();
+        learning_rule.learn_organization(self);
+        let p_flip = learning_rule.p_flip(&self.config);
+        for (focal, contradicted) in is_contradicted.iter().enumerate() {
+            for dimension in contradicted.ones() {
+                if self.rng.random::<f64>() < p_flip {
+                    self.belief_of[focal].flip(dimension);
                 }
             }
             self.set_performance_of(focal);
         }
     }
 
-    fn get_performance_of(&self, focal: usize) -> usize {
-        let belief_of_focal = &self.belief_of[focal];
-        let mut performance_of_focal:usize = 0;
-        'bundle: for bundle in 0..params::M_OF_BUNDLE{
-            for element in 0..params::M_IN_BUNDLE{
-                if self.reality[bundle][element] != belief_of_focal[bundle][element] {
-                    continue 'bundle;
-                }
-            }
-            performance_of_focal += params::M_IN_BUNDLE;
-        }
-        performance_of_focal
+    fn get_performance_of(&self, focal: usize) -> f64 {
+        self.get_performance_of_beliefs(&self.belief_of[focal])
+    }
+
+    /// Performance of any set of beliefs, such as the organizational code.
+    pub fn get_performance_of_beliefs(&self, beliefs: &BitVector) -> f64 {
+        self.task_environment.performance(&self.reality, beliefs)
     }
 
     /// Equivalent to void setPerformance(int focal).
@@ -699,7 +688,7 @@ impl Scenario {
 
     /// Equivalent to void setPerformance().
     fn set_performance(&mut self) {
-        for focal in 0..params::N {
+        for focal in 0..self.config.n {
             self.set_performance_of(focal);
         }
     }
@@ -719,13 +708,13 @@ impl Scenario {
         writeln!(&mut writer, "SOURCE,TARGET,TIE_ENFORCED").unwrap();
 
         // Edges
-        for focal in 0..params::N {
-            for target in focal..params::N {
+        for focal in 0..self.config.n {
+            for target in focal..self.config.n {
                 if focal == target {
                     continue;
                 }
-                if self.network[focal][target] {
-                    let tie_enforced = self.network_formal[focal][target];
+                if self.network.get(focal, target) {
+                    let tie_enforced = self.network_formal.get(focal, target);
                     writeln!(
                         &mut writer,
                         "{},{},{}",
@@ -737,7 +726,7 @@ impl Scenario {
         }
 
         // Individual lines
-        for focal in 0..params::N {
+        for focal in 0..self.config.n {
             writeln!(&mut writer, "{},,", focal).unwrap();
         }
     }
@@ -745,12 +734,11 @@ impl Scenario {
     /// doTurnover(): each individual leaves with probability turnoverRate; replaced by a new random one.
     pub fn do_turnover(&mut self) {
         self.is_stale = false;
-        for focal in 0..params::N {
+        for focal in 0..self.config.n {
             if self.rng.random::<f64>() < self.turnover_rate {
-                for bundle in 0..params::M_OF_BUNDLE {
-                    for element in 0..params::M_IN_BUNDLE{
-                        self.belief_of[focal][bundle][element] = self.rng.random::<bool>();
-                    }
+                for dimension in 0..self.config.m {
+                    let value = self.rng.random::<bool>();
+                    self.belief_of[focal].set(dimension, value);
                 }
                 self.set_performance_of(focal);
             }
@@ -760,13 +748,414 @@ impl Scenario {
     /// doTurbulence(): each dimension of reality is flipped with probability turbulenceRate.
     pub fn do_turbulence(&mut self) {
         self.is_stale = false;
-        for bundle in 0..params::M_OF_BUNDLE {
-            for element in 0..params::M_IN_BUNDLE{
-                if self.rng.random::<f64>() < self.turbulence_rate {
-                    self.reality[bundle][element] = !self.reality[bundle][element];
-                }
+        for dimension in 0..self.config.m {
+            if self.rng.random::<f64>() < self.turbulence_rate {
+                self.reality.flip(dimension);
             }
         }
         self.set_performance();
     }
-}
\ No newline at end of file
+}
+
+/// The informal ties at the start of a round of tie breaks, as the items of its sampler,
+/// with the items at each agent so that a break only revisits the ties it can change.
+struct InformalTies {
+    dyads: Vec<(usize, usize)>,
+    items_of: Vec<Vec<usize>>,
+}
+
+impl InformalTies {
+    fn new(network_informal: &AdjacencyMatrix) -> Self {
+        let n = network_informal.len();
+        let mut dyads = Vec::new();
+        let mut items_of = vec![Vec::new(); n];
+        for focal in 0..n {
+            for target in network_informal.neighbors(focal).filter(|&target| target > focal) {
+                items_of[focal].push(dyads.len());
+                items_of[target].push(dyads.len());
+                dyads.push((focal, target));
+            }
+        }
+        InformalTies { dyads, items_of }
+    }
+}
+
+/// The analyzer of a formal or informal layer, kept only when `layered_metrics` is set.
+fn new_layer_analyzer(config: &Arc<ExperimentConfig>, rng: &ScenarioRng, layer: usize) -> Option<NetworkAnalyzer> {
+    config.layered_metrics.then(|| NetworkAnalyzer::new(config, rng, layer))
+}
+
+/// Position of the dyad {i, j} in the order (0, 1), (0, 2), ..., (0, N - 1), (1, 2), ....
+fn dyad_index(n: usize, i: usize, j: usize) -> usize {
+    let (low, high) = if i < j { (i, j) } else { (j, i) };
+    low * (2 * n - low - 1) / 2 + (high - low - 1)
+}
+
+/// The dyad at a position of that order.
+fn dyad_of(n: usize, mut index: usize) -> (usize, usize) {
+    let mut low = 0;
+    while index >= n - low - 1 {
+        index -= n - low - 1;
+        low += 1;
+    }
+    (low, low + 1 + index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::learning_rule::LEARNING_RULES;
+    use crate::social_dynamics::SOCIAL_DYNAMICS;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    const NUM_DRAW: usize = 200_000;
+    const NUM_FLIP: usize = 6;
+
+    /// Weighs every dyad 0, so that every draw falls back to a uniform one.
+    struct Indifferent;
+
+    impl SocialDynamics for Indifferent {
+        fn name(&self) -> &'static str {
+            "indifferent"
+        }
+
+        fn code(&self) -> &'static str {
+            "Indif"
+        }
+
+        fn formation_weight(&self, _: &Scenario, _: usize, _: usize) -> f64 {
+            0.0
+        }
+
+        fn break_weight(&self, _: &Scenario, _: usize, _: usize) -> f64 {
+            0.0
+        }
+    }
+
+    fn social_dynamics() -> impl Iterator<Item = &'static dyn SocialDynamics> {
+        SOCIAL_DYNAMICS.iter().copied().chain([&Indifferent as &dyn SocialDynamics])
+    }
+
+    fn scenario(social_dynamics: &'static dyn SocialDynamics, seed: u64) -> Scenario {
+        let mut config = ExperimentConfig { n: 30, ..ExperimentConfig::default() };
+        config.set_second_order_params();
+        let mut scenario = Scenario::new(&Arc::new(config), ScenarioRng::seed_from_u64(seed), social_dynamics, 3, 0.5, 0.0, 0.0);
+        // Informal ties beyond the hierarchy, so that the weights spread out.
+        scenario.do_tie_formation(40);
+        scenario
+    }
+
+    /// Probability of drawing each dyad under the linear scan `do_tie_formation` ran before the sampler:
+    /// proportional to the formation weight over the open dyads, or uniform over the untied dyads outside
+    /// `network_limited` when none has weight.
+    fn linear_scan_formation(scenario: &Scenario) -> HashMap<(usize, usize), f64> {
+        let weight: Vec<f64> = scenario.iterator_dyad.iter().map(|&(focal, target)| {
+            if !scenario.network.get(focal, target)
+                && focal != target
+                && !scenario.network_limited.get(focal, target)
+                && scenario.degree_informal[focal] < scenario.config.informal_max_num
+                && scenario.degree_informal[target] < scenario.config.informal_max_num
+            {
+                scenario.social_dynamics.formation_weight(scenario, focal, target)
+            } else {
+                0.0
+            }
+        }).collect();
+        let is_open: Vec<bool> = scenario.iterator_dyad.iter()
+            .map(|&(focal, target)| focal != target && !scenario.network.get(focal, target) && !scenario.network_limited.get(focal, target))
+            .collect();
+        normalize(scenario, weight, &is_open)
+    }
+
+    /// The same for `do_tie_break`: proportional to the shortfall from the largest break weight
+    /// over the informal ties, or uniform over the informal ties when there is no shortfall.
+    fn linear_scan_break(scenario: &Scenario) -> HashMap<(usize, usize), f64> {
+        let weight: Vec<f64> = scenario.iterator_dyad.iter().map(|&(focal, target)| {
+            if scenario.network_informal.get(focal, target) { scenario.social_dynamics.break_weight(scenario, focal, target) } else { 0.0 }
+        }).collect();
+        let max = weight.iter().cloned().fold(f64::MIN, f64::max);
+        let is_tied: Vec<bool> = scenario.iterator_dyad.iter().map(|&(focal, target)| scenario.network_informal.get(focal, target)).collect();
+        normalize(scenario, weight.iter().map(|&w| if w != 0.0 { max - w } else { 0.0 }).collect(), &is_tied)
+    }
+
+    /// The weights as probabilities, or uniform over the `fallback` dyads when they sum to 0.
+    fn normalize(scenario: &Scenario, weight: Vec<f64>, fallback: &[bool]) -> HashMap<(usize, usize), f64> {
+        let total: f64 = weight.iter().sum();
+        let num_fallback = fallback.iter().filter(|&&is_fallback| is_fallback).count();
+        scenario.iterator_dyad.iter().zip(weight).zip(fallback).map(|((&dyad, w), &is_fallback)| {
+            let probability = if total != 0.0 { w / total } else if is_fallback { 1.0 / num_fallback as f64 } else { 0.0 };
+            (dyad, probability)
+        }).collect()
+    }
+
+    /// The degrees kept by `set_informal_tie` against those counted on the networks, and the informal
+    /// ties against the formal ones.
+    fn assert_degrees_consistent(scenario: &Scenario) {
+        for focal in 0..scenario.config.n {
+            assert_eq!(scenario.degree[focal], scenario.network.degree(focal) as isize, "degree of {}", focal);
+            assert_eq!(scenario.degree_formal[focal], scenario.network_formal.degree(focal) as isize, "formal degree of {}", focal);
+            assert_eq!(scenario.degree_informal[focal], scenario.network_informal.degree(focal) as isize, "informal degree of {}", focal);
+            for target in scenario.network_informal.neighbors(focal) {
+                assert!(!scenario.network_formal.get(focal, target), "informal tie {:?} is formal", (focal, target));
+                assert!(scenario.network.get(focal, target), "informal tie {:?} is not in the network", (focal, target));
+            }
+        }
+    }
+
+    /// Pearson's chi-squared goodness of fit of `NUM_DRAW` draws, pooling the dyads expected fewer than 5 times,
+    /// against the upper 0.01% point of its distribution (Wilson-Hilferty approximation).
+    fn assert_same_distribution(expected: &HashMap<(usize, usize), f64>, mut draw: impl FnMut() -> (usize, usize)) {
+        let mut observed: HashMap<(usize, usize), usize> = HashMap::new();
+        for _ in 0..NUM_DRAW {
+            *observed.entry(draw()).or_insert(0) += 1;
+        }
+        for dyad in observed.keys() {
+            assert!(expected[dyad] > 0.0, "drew {:?}, which the linear scan never draws", dyad);
+        }
+
+        let mut statistic = 0.0;
+        let mut num_bin = 0;
+        let (mut pooled_expected, mut pooled_observed) = (0.0, 0.0);
+        for (dyad, &probability) in expected {
+            let count_expected = probability * NUM_DRAW as f64;
+            let count_observed = observed.get(dyad).copied().unwrap_or(0) as f64;
+            if count_expected >= 5.0 {
+                statistic += (count_observed - count_expected).powi(2) / count_expected;
+                num_bin += 1;
+            } else {
+                pooled_expected += count_expected;
+                pooled_observed += count_observed;
+            }
+        }
+        if pooled_expected > 0.0 {
+            statistic += (pooled_observed - pooled_expected).powi(2) / pooled_expected;
+            num_bin += 1;
+        }
+        assert!(num_bin > 1);
+        let df = (num_bin - 1) as f64;
+        let critical = df * (1.0 - 2.0 / (9.0 * df) + 3.719 * (2.0 / (9.0 * df)).sqrt()).powi(3);
+        assert!(statistic < critical, "chi-squared {} with {} degrees of freedom exceeds {}", statistic, df, critical);
+    }
+
+    #[test]
+    fn tie_formation_draws_as_the_linear_scan() {
+        for (i, social_dynamics) in social_dynamics().enumerate() {
+            let mut scenario = scenario(social_dynamics, 11 + i as u64);
+            let mut sampler = scenario.get_tie_formation_sampler();
+            for _ in 0..NUM_FLIP {
+                let (focal, target) = scenario.draw_tie_formation(&sampler).unwrap();
+                scenario.set_informal_tie(focal, target, true);
+                scenario.update_tie_formation_weights(&mut sampler, focal, target);
+            }
+            let rebuilt = scenario.get_tie_formation_sampler();
+            for item in 0..sampler.len() {
+                assert_eq!(sampler.weight(item), rebuilt.weight(item), "dyad {:?}", dyad_of(scenario.config.n, item));
+            }
+
+            let expected = linear_scan_formation(&scenario);
+            assert_same_distribution(&expected, || scenario.draw_tie_formation(&sampler).unwrap());
+        }
+    }
+
+    #[test]
+    fn tie_break_draws_as_the_linear_scan() {
+        for (i, social_dynamics) in social_dynamics().enumerate() {
+            let mut scenario = scenario(social_dynamics, 23 + i as u64);
+            let (ties, mut sampler) = scenario.get_tie_break_sampler();
+            for _ in 0..NUM_FLIP {
+                let (focal, target) = scenario.draw_tie_break(&ties, &sampler).unwrap();
+                scenario.set_informal_tie(focal, target, false);
+                scenario.update_tie_break_weights(&ties, &mut sampler, focal, target);
+            }
+            for (item, &(focal, target)) in ties.dyads.iter().enumerate() {
+                assert_eq!(sampler.weight(item), scenario.get_tie_break_weight(focal, target), "tie {:?}", (focal, target));
+            }
+
+            let expected = linear_scan_break(&scenario);
+            assert_same_distribution(&expected, || scenario.draw_tie_break(&ties, &sampler).unwrap());
+        }
+    }
+
+    #[test]
+    fn rewiring_keeps_the_degrees_consistent() {
+        for (i, social_dynamics) in social_dynamics().enumerate() {
+            let mut scenario = scenario(social_dynamics, 31 + i as u64);
+            assert_degrees_consistent(&scenario);
+            for _ in 0..20 {
+                scenario.do_tie_formation(5);
+                scenario.do_tie_break(5);
+                assert_degrees_consistent(&scenario);
+            }
+            // Breaking more ties than there are stops once none is left.
+            scenario.do_tie_break(scenario.config.n_dyad);
+            assert_degrees_consistent(&scenario);
+            assert!((0..scenario.config.n).all(|focal| scenario.degree_informal[focal] == 0));
+        }
+    }
+
+    /// The number of informal ties.
+    fn num_informal_tie(scenario: &Scenario) -> isize {
+        scenario.degree_informal.iter().sum::<isize>() / 2
+    }
+
+    #[test]
+    fn homophily_without_strength_breaks_ties_uniformly() {
+        let mut config = ExperimentConfig { n: 30, homophily_strength: 0.0, ..ExperimentConfig::default() };
+        config.set_second_order_params();
+        let homophily = crate::social_dynamics::find("homophily").unwrap();
+        let mut scenario = Scenario::new(&Arc::new(config), ScenarioRng::seed_from_u64(41), homophily, 3, 0.5, 0.0, 0.0);
+        scenario.do_tie_formation(40);
+        let num_tie = num_informal_tie(&scenario);
+        for _ in 0..50 {
+            // Every tie weighs the same, so each break falls back to a uniform tie.
+            let (_, sampler) = scenario.get_tie_break_sampler();
+            assert!(sampler.sample_shortfall(&mut scenario.rng).is_none());
+            scenario.do_rewiring(3, 3);
+            assert_degrees_consistent(&scenario);
+            assert_eq!(num_informal_tie(&scenario), num_tie);
+        }
+    }
+
+    /// The number of informal ties in `after` missing from `before`, and how many of them have their better end
+    /// among the agents who performed above the median in `before`.
+    fn count_new_ties_above_median(before: &Scenario, after: &Scenario) -> (usize, usize) {
+        let mut sorted = before.performance_of.clone();
+        sorted.sort_by(f64::total_cmp);
+        let median = sorted[sorted.len() / 2];
+        let new_ties: Vec<_> = before.iterator_dyad.iter()
+            .filter(|&&(focal, target)| after.network_informal.get(focal, target) && !before.network_informal.get(focal, target))
+            .collect();
+        let num_above = new_ties.iter()
+            .filter(|&&&(focal, target)| before.performance_of[focal].max(before.performance_of[target]) > median)
+            .count();
+        (new_ties.len(), num_above)
+    }
+
+    #[test]
+    fn performance_seeking_ties_land_on_better_performers() {
+        let mut config = ExperimentConfig { n: 30, p_learning: 0.1, informal_rewiring_prop: 0.5, ..ExperimentConfig::default() };
+        config.set_second_order_params();
+        let num_formation = config.informal_rewiring_num;
+        assert!(num_formation > 0);
+        let performance_seeking = crate::social_dynamics::find("performance_seeking").unwrap();
+        let mut scenario = Scenario::new(&Arc::new(config), ScenarioRng::seed_from_u64(43), performance_seeking, 3, 0.5, 0.0, 0.0);
+        let (mut num_new, mut num_above) = (0, 0);
+        let (mut num_new_uniform, mut num_above_uniform) = (0, 0);
+        for step in 0..30 {
+            scenario.step_forward();
+            assert_degrees_consistent(&scenario);
+            let mut seeking = scenario.get_clone(ScenarioRng::seed_from_u64(step));
+            seeking.do_tie_formation(num_formation);
+            let (new, above) = count_new_ties_above_median(&scenario, &seeking);
+            num_new += new;
+            num_above += above;
+            let mut uniform = scenario.get_clone(ScenarioRng::seed_from_u64(step));
+            uniform.do_random_rewiring(num_formation, 0);
+            let (new, above) = count_new_ties_above_median(&scenario, &uniform);
+            num_new_uniform += new;
+            num_above_uniform += above;
+        }
+        let share = num_above as f64 / num_new as f64;
+        let share_uniform = num_above_uniform as f64 / num_new_uniform as f64;
+        assert!(share > share_uniform + 0.05, "{share} of the sought ties and {share_uniform} of the uniform ones reach above the median");
+    }
+
+    #[test]
+    fn clones_share_the_task_environment() {
+        let mut config = ExperimentConfig { n: 30, task_environment: "nk".to_string(), nk_k: 3, ..ExperimentConfig::default() };
+        config.set_second_order_params();
+        let scenario = Scenario::new(&Arc::new(config), ScenarioRng::seed_from_u64(47), SOCIAL_DYNAMICS[0], 3, 0.5, 0.0, 0.0);
+        let clone = scenario.get_clone(ScenarioRng::seed_from_u64(53));
+        assert!(Arc::ptr_eq(&clone.task_environment, &scenario.task_environment));
+        assert_eq!(clone.performance_of, scenario.performance_of);
+    }
+
+    /// The majority of `voters` on one dimension contradicts a belief when it is strict and differs.
+    fn is_outvoted(belief: bool, num_one: f64, num_voter: f64) -> bool {
+        if belief { 2.0 * num_one < num_voter } else { 2.0 * num_one > num_voter }
+    }
+
+    /// Whether each rule would flip a belief, worked out one dimension at a time.
+    fn is_contradicted_per_dimension(scenario: &Scenario, focal: usize, dimension: usize) -> bool {
+        let belief = scenario.belief_of[focal].get(dimension);
+        let neighbors: Vec<usize> = scenario.network.neighbors(focal).collect();
+        let performance = &scenario.performance_of;
+        match scenario.learning_rule.name() {
+            "superior_majority" | "conformity" => {
+                let voters: Vec<usize> = neighbors.into_iter()
+                    .filter(|&neighbor| scenario.learning_rule.name() == "conformity" || performance[neighbor] > performance[focal])
+                    .collect();
+                let num_one = voters.iter().filter(|&&voter| scenario.belief_of[voter].get(dimension)).count();
+                is_outvoted(belief, num_one as f64, voters.len() as f64)
+            }
+            "best_neighbor" => {
+                let best = neighbors.iter().copied().rev().max_by(|&a, &b| performance[a].total_cmp(&performance[b]));
+                best.is_some_and(|best| performance[best] > performance[focal] && scenario.belief_of[best].get(dimension) != belief)
+            }
+            "performance_weighted" => {
+                let weight_one: f64 = neighbors.iter().filter(|&&neighbor| scenario.belief_of[neighbor].get(dimension)).map(|&neighbor| performance[neighbor]).sum();
+                let weight_total: f64 = neighbors.iter().map(|&neighbor| performance[neighbor]).sum();
+                is_outvoted(belief, weight_one, weight_total)
+            }
+            "organizational_code" => scenario.organizational_code.as_ref().unwrap().get(dimension) != belief,
+            name => panic!("no reference for the learning rule {}", name),
+        }
+    }
+
+    fn scenario_learning(learning_rule: &str, seed: u64) -> Scenario {
+        let mut config = ExperimentConfig { n: 30, learning_rule: learning_rule.to_string(), p_code_learning: 1.0, ..ExperimentConfig::default() };
+        config.set_second_order_params();
+        let mut scenario = Scenario::new(&Arc::new(config), ScenarioRng::seed_from_u64(seed), SOCIAL_DYNAMICS[0], 3, 0.5, 0.0, 0.0);
+        scenario.do_tie_formation(40);
+        scenario
+    }
+
+    #[test]
+    fn learning_rules_contradict_as_counted_per_dimension() {
+        for (i, learning_rule) in LEARNING_RULES.iter().enumerate() {
+            let scenario = scenario_learning(learning_rule.name(), 31 + i as u64);
+            for focal in 0..scenario.config.n {
+                let contradicted = learning_rule.contradicted(&scenario, focal);
+                for dimension in 0..scenario.config.m {
+                    assert_eq!(
+                        contradicted.get(dimension),
+                        is_contradicted_per_dimension(&scenario, focal, dimension),
+                        "{} at agent {} and dimension {}", learning_rule.name(), focal, dimension
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn organizational_code_takes_the_view_of_the_elite() {
+        let mut scenario = scenario_learning("organizational_code", 37);
+        let code = scenario.organizational_code.clone().unwrap();
+        let performance_code = scenario.get_performance_of_beliefs(&code);
+        let elite: Vec<usize> = (0..scenario.config.n).filter(|&focal| scenario.performance_of[focal] > performance_code).collect();
+        assert!(!elite.is_empty());
+        scenario.learning_rule.learn_organization(&mut scenario);
+        // At P_CODE_LEARNING = 1 the code follows every strict majority of the elite and keeps its view otherwise.
+        let learned = scenario.organizational_code.as_ref().unwrap();
+        for dimension in 0..scenario.config.m {
+            let num_one = elite.iter().filter(|&&focal| scenario.belief_of[focal].get(dimension)).count();
+            let expected = if is_outvoted(code.get(dimension), num_one as f64, elite.len() as f64) { !code.get(dimension) } else { code.get(dimension) };
+            assert_eq!(learned.get(dimension), expected, "dimension {}", dimension);
+        }
+    }
+
+    #[test]
+    fn dyad_index_runs_through_the_dyads_in_order() {
+        let n = 7;
+        let mut index = 0;
+        for i in 0..n {
+            for j in (i + 1)..n {
+                assert_eq!(dyad_index(n, i, j), index);
+                assert_eq!(dyad_index(n, j, i), index);
+                assert_eq!(dyad_of(n, index), (i, j));
+                index += 1;
+            }
+        }
+    }
+}
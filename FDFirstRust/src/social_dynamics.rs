@@ -0,0 +1,161 @@
+use std::cmp;
+use crate::scenario::Scenario;
+
+/// A mechanism by which agents form and break informal ties.
+/// Tie formation draws an open dyad with probability proportional to `formation_weight`;
+/// tie break draws an informal tie with probability proportional to its shortfall from the largest
+/// `break_weight`, so the weakest ties go first.
+pub trait SocialDynamics: Sync {
+    /// Name under which the mechanism is listed in `social_dynamics` of the design.
+    fn name(&self) -> &'static str;
+
+    /// Short code used in file names, e.g. `NetCl`.
+    fn code(&self) -> &'static str;
+
+    /// Weight of a tie between two agents that are not yet tied; 0 keeps the dyad from being drawn.
+    fn formation_weight(&self, scenario: &Scenario, focal: usize, target: usize) -> f64;
+
+    /// Strength of an existing informal tie.
+    fn break_weight(&self, scenario: &Scenario, focal: usize, target: usize) -> f64;
+}
+
+/// Agents tie up with the agents they share neighbors with.
+pub struct NetworkClosure;
+
+impl NetworkClosure {
+    /// One plus the common neighbors, relative to the larger degree the tie would give (1 avoids a weight of 0).
+    fn rewiring_weight(scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        let preference_score = 1.0 + scenario.network.common_neighbors(focal, target) as f64;
+        let denom = cmp::max(scenario.degree[focal], scenario.degree[target]) as f64
+            + if scenario.network.get(focal, target) { 0.0 } else { 1.0 };
+        preference_score / denom
+    }
+}
+
+impl SocialDynamics for NetworkClosure {
+    fn name(&self) -> &'static str {
+        "network_closure"
+    }
+
+    fn code(&self) -> &'static str {
+        "NetCl"
+    }
+
+    fn formation_weight(&self, scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        Self::rewiring_weight(scenario, focal, target)
+    }
+
+    fn break_weight(&self, scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        Self::rewiring_weight(scenario, focal, target)
+    }
+}
+
+/// Agents tie up with well-connected agents.
+pub struct PreferentialAttachment;
+
+impl PreferentialAttachment {
+    /// The smaller of the two degrees.
+    fn rewiring_weight(scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        let df = scenario.degree[focal] as f64;
+        let dt = scenario.degree[target] as f64;
+        df.min(dt)
+    }
+}
+
+impl SocialDynamics for PreferentialAttachment {
+    fn name(&self) -> &'static str {
+        "preferential_attachment"
+    }
+
+    fn code(&self) -> &'static str {
+        "PrfAt"
+    }
+
+    fn formation_weight(&self, scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        Self::rewiring_weight(scenario, focal, target)
+    }
+
+    fn break_weight(&self, scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        Self::rewiring_weight(scenario, focal, target)
+    }
+}
+
+/// Agents tie up with agents who know what they know.
+/// Similarity is the share of beliefs two agents agree on, blended with the closeness of their performance
+/// by `homophily_performance_weight`; `homophily_strength` interpolates from random rewiring to similarity alone.
+pub struct Homophily;
+
+impl Homophily {
+    /// Similarity in [0, 1].
+    fn similarity(scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        let m = scenario.config.m as f64;
+        let belief_similarity = 1.0 - scenario.belief_of[focal].num_differing(&scenario.belief_of[target]) as f64 / m;
+        let performance_gap = (scenario.performance_of[focal] - scenario.performance_of[target]).abs();
+        let performance_similarity = 1.0 - performance_gap / m;
+        let weight = scenario.config.homophily_performance_weight;
+        (1.0 - weight) * belief_similarity + weight * performance_similarity
+    }
+
+    /// The similarity drawn toward 1, where every dyad weighs the same, as the strength falls.
+    fn rewiring_weight(scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        let strength = scenario.config.homophily_strength;
+        1.0 - strength + strength * Self::similarity(scenario, focal, target)
+    }
+}
+
+impl SocialDynamics for Homophily {
+    fn name(&self) -> &'static str {
+        "homophily"
+    }
+
+    fn code(&self) -> &'static str {
+        "Homph"
+    }
+
+    fn formation_weight(&self, scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        Self::rewiring_weight(scenario, focal, target)
+    }
+
+    fn break_weight(&self, scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        Self::rewiring_weight(scenario, focal, target)
+    }
+}
+
+/// Agents seek ties to better performers and let go of ties to worse ones.
+pub struct PerformanceSeeking;
+
+impl PerformanceSeeking {
+    /// How strongly `focal` is drawn to `target`: their performance ratio, with 1 added to both so a
+    /// performance of 0 neither attracts nothing nor divides by zero.
+    fn pull(scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        (1.0 + scenario.performance_of[target]) / (1.0 + scenario.performance_of[focal])
+    }
+}
+
+impl SocialDynamics for PerformanceSeeking {
+    fn name(&self) -> &'static str {
+        "performance_seeking"
+    }
+
+    fn code(&self) -> &'static str {
+        "PrfSk"
+    }
+
+    /// The pull of the worse performer toward the better one, who is the one sought.
+    fn formation_weight(&self, scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        Self::pull(scenario, focal, target).max(Self::pull(scenario, target, focal))
+    }
+
+    /// One plus the performance of the worse performer, so ties to the worst performers go first.
+    fn break_weight(&self, scenario: &Scenario, focal: usize, target: usize) -> f64 {
+        1.0 + scenario.performance_of[focal].min(scenario.performance_of[target])
+    }
+}
+
+/// Every mechanism that can be named in a design. Adding one only takes a new entry here.
+pub static SOCIAL_DYNAMICS: &[&dyn SocialDynamics] = &[&NetworkClosure, &PreferentialAttachment, &Homophily, &PerformanceSeeking];
+
+/// The registered mechanism called `name`.
+pub fn find(name: &str) -> Option<&'static dyn SocialDynamics> {
+    SOCIAL_DYNAMICS.iter().copied().find(|dynamics| dynamics.name() == name)
+}
@@ -0,0 +1,240 @@
+use rand::seq::index;
+use rand::Rng;
+use std::cmp;
+use std::sync::Arc;
+use crate::bit_vector::{BitVector, BundleMasks};
+use crate::params::ExperimentConfig;
+use crate::scenario::ScenarioRng;
+
+/// How the beliefs of an agent pay off against reality.
+/// Performance runs from 0 up to M, which beliefs matching reality in every dimension attain.
+pub trait TaskEnvironment: Send + Sync {
+    fn performance(&self, reality: &BitVector, beliefs: &BitVector) -> f64;
+}
+
+/// A kind of environment, built afresh for every scenario from the parameters of the design.
+pub trait TaskEnvironmentKind: Sync {
+    /// Name under which the environment is chosen as `task_environment` of the design.
+    fn name(&self) -> &'static str;
+
+    /// Short code of the environment under `config`, e.g. `Bundles`.
+    fn code(&self, config: &ExperimentConfig) -> String;
+
+    /// The environment of a scenario; it may draw from `rng`.
+    fn build(&self, config: &ExperimentConfig, rng: &mut ScenarioRng) -> Arc<dyn TaskEnvironment>;
+}
+
+pub struct BundlesKind;
+
+impl TaskEnvironmentKind for BundlesKind {
+    fn name(&self) -> &'static str {
+        "bundles"
+    }
+
+    fn code(&self, _: &ExperimentConfig) -> String {
+        "Bundles".to_string()
+    }
+
+    fn build(&self, config: &ExperimentConfig, _: &mut ScenarioRng) -> Arc<dyn TaskEnvironment> {
+        Arc::new(Bundles::new(config.m_of_bundle, config.m_in_bundle))
+    }
+}
+
+pub struct NkKind;
+
+impl TaskEnvironmentKind for NkKind {
+    fn name(&self) -> &'static str {
+        "nk"
+    }
+
+    fn code(&self, config: &ExperimentConfig) -> String {
+        format!("NK({}{})", config.nk_k, config.nk_interaction)
+    }
+
+    /// Draws the interactions and the contributions of the landscape.
+    fn build(&self, config: &ExperimentConfig, rng: &mut ScenarioRng) -> Arc<dyn TaskEnvironment> {
+        Arc::new(NkLandscape::new(config.m, config.nk_k, config.get_nk_interaction(), rng))
+    }
+}
+
+/// Every environment that can be named as `task_environment` of the design.
+pub static TASK_ENVIRONMENTS: &[&dyn TaskEnvironmentKind] = &[&BundlesKind, &NkKind];
+
+/// The registered environment called `name`.
+pub fn find(name: &str) -> Option<&'static dyn TaskEnvironmentKind> {
+    TASK_ENVIRONMENTS.iter().copied().find(|kind| kind.name() == name)
+}
+
+/// How the dimensions of an NK landscape interact.
+pub trait NkInteraction: Sync {
+    /// Name under which the interaction is chosen as `nk_interaction` of the design.
+    fn name(&self) -> &'static str;
+
+    /// The dimensions `dimension` interacts with, out of `m`, in increasing order.
+    fn dependencies(&self, m: usize, k: usize, dimension: usize, rng: &mut ScenarioRng) -> Vec<usize>;
+}
+
+/// Any K others.
+pub struct RandomInteraction;
+
+impl NkInteraction for RandomInteraction {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn dependencies(&self, m: usize, k: usize, dimension: usize, rng: &mut ScenarioRng) -> Vec<usize> {
+        let mut others: Vec<usize> = index::sample(rng, m - 1, k)
+            .into_iter()
+            .map(|other| if other >= dimension { other + 1 } else { other })
+            .collect();
+        others.sort_unstable();
+        others
+    }
+}
+
+/// The others of its block of K + 1 consecutive dimensions.
+pub struct BlockDiagonal;
+
+impl NkInteraction for BlockDiagonal {
+    fn name(&self) -> &'static str {
+        "block_diagonal"
+    }
+
+    fn dependencies(&self, m: usize, k: usize, dimension: usize, _: &mut ScenarioRng) -> Vec<usize> {
+        let start = dimension / (k + 1) * (k + 1);
+        (start..cmp::min(start + k + 1, m)).filter(|&other| other != dimension).collect()
+    }
+}
+
+/// K of the dimensions before it, or all of them when there are fewer.
+pub struct Hierarchical;
+
+impl NkInteraction for Hierarchical {
+    fn name(&self) -> &'static str {
+        "hierarchical"
+    }
+
+    fn dependencies(&self, _: usize, k: usize, dimension: usize, rng: &mut ScenarioRng) -> Vec<usize> {
+        let mut before = index::sample(rng, dimension, cmp::min(k, dimension)).into_vec();
+        before.sort_unstable();
+        before
+    }
+}
+
+/// Every interaction structure that can be named as `nk_interaction` of the design.
+pub static NK_INTERACTIONS: &[&dyn NkInteraction] = &[&RandomInteraction, &BlockDiagonal, &Hierarchical];
+
+/// The registered interaction structure called `name`.
+pub fn find_interaction(name: &str) -> Option<&'static dyn NkInteraction> {
+    NK_INTERACTIONS.iter().copied().find(|interaction| interaction.name() == name)
+}
+
+/// A bundle of M_IN_BUNDLE dimensions counts in full when every element matches reality and not at all otherwise.
+pub struct Bundles {
+    masks: BundleMasks,
+    m_in_bundle: usize,
+}
+
+impl Bundles {
+    pub fn new(m_of_bundle: usize, m_in_bundle: usize) -> Self {
+        Bundles { masks: BundleMasks::new(m_of_bundle, m_in_bundle), m_in_bundle }
+    }
+}
+
+impl TaskEnvironment for Bundles {
+    fn performance(&self, reality: &BitVector, beliefs: &BitVector) -> f64 {
+        (self.masks.num_agreeing(reality, beliefs) * self.m_in_bundle) as f64
+    }
+}
+
+/// Kauffman's NK landscape: every dimension contributes a value in [0, 1] that depends on whether it and
+/// the K dimensions it interacts with match reality. A dimension contributes 1 when all of them match and
+/// a uniform draw otherwise, so matching reality stays the global optimum while K sets the ruggedness.
+/// The draws are hashed from a seed of the landscape rather than tabulated, which keeps any K cheap.
+pub struct NkLandscape {
+    dependencies: Vec<Vec<usize>>,
+    seed: u64,
+}
+
+impl NkLandscape {
+    /// Each dimension interacts with the K dimensions `interaction` lays out.
+    pub fn new(m: usize, k: usize, interaction: &dyn NkInteraction, rng: &mut ScenarioRng) -> Self {
+        let dependencies = (0..m).map(|dimension| interaction.dependencies(m, k, dimension, rng)).collect();
+        NkLandscape { dependencies, seed: rng.random() }
+    }
+
+    /// The contribution of `dimension` given the mismatches with reality of it (bit 0) and of
+    /// its dependencies (the bits after).
+    fn contribution(&self, dimension: usize, pattern: u64) -> f64 {
+        if pattern == 0 {
+            return 1.0;
+        }
+        let mut x = self.seed ^ (dimension as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ pattern.wrapping_mul(0xD1B5_4A32_D192_ED03);
+        // SplitMix64 finalizer.
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        x ^= x >> 31;
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl TaskEnvironment for NkLandscape {
+    fn performance(&self, reality: &BitVector, beliefs: &BitVector) -> f64 {
+        let words = reality.words().iter().zip(beliefs.words()).map(|(reality, belief)| reality ^ belief).collect();
+        let mismatch = BitVector::from_words(reality.len(), words);
+        self.dependencies
+            .iter()
+            .enumerate()
+            .map(|(dimension, dependencies)| {
+                let pattern = std::iter::once(dimension)
+                    .chain(dependencies.iter().copied())
+                    .enumerate()
+                    .fold(0, |pattern, (bit, other)| pattern | (mismatch.get(other) as u64) << bit);
+                self.contribution(dimension, pattern)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const M: usize = 20;
+
+    #[test]
+    fn matching_reality_is_the_global_optimum() {
+        let mut rng = ScenarioRng::seed_from_u64(5);
+        for &interaction in NK_INTERACTIONS {
+            for k in [0, 3, M - 1] {
+                let landscape = NkLandscape::new(M, k, interaction, &mut rng);
+                let reality = BitVector::from_words(M, vec![rng.random()]);
+                assert_eq!(landscape.performance(&reality, &reality), M as f64);
+                for _ in 0..100 {
+                    let beliefs = BitVector::from_words(M, vec![rng.random()]);
+                    if beliefs != reality {
+                        assert!(landscape.performance(&reality, &beliefs) < M as f64, "{} at K = {}", interaction.name(), k);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dimensions_interact_as_laid_out() {
+        let mut rng = ScenarioRng::seed_from_u64(6);
+        let k = 3;
+        let block_diagonal = NkLandscape::new(M, k, &BlockDiagonal, &mut rng);
+        assert_eq!(block_diagonal.dependencies[5], vec![4, 6, 7]);
+        let hierarchical = NkLandscape::new(M, k, &Hierarchical, &mut rng);
+        assert_eq!(hierarchical.dependencies[1], vec![0]);
+        let random = NkLandscape::new(M, k, &RandomInteraction, &mut rng);
+        for (dimension, (before, others)) in hierarchical.dependencies.iter().zip(&random.dependencies).enumerate() {
+            assert_eq!(before.len(), cmp::min(k, dimension));
+            assert!(before.iter().all(|&other| other < dimension));
+            assert_eq!(others.len(), k);
+            assert!(!others.contains(&dimension));
+        }
+    }
+}
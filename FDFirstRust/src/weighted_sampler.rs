@@ -0,0 +1,190 @@
+use rand::Rng;
+
+/// Draws items with probability proportional to non-negative weights that change one at a time.
+/// Two Fenwick trees hold the prefix sums of the weights and the prefix counts of the items of positive
+/// weight, and a segment tree the largest weight and the smallest positive one, so a weight is updated
+/// and an item drawn in O(log k) for k items.
+/// Items of zero weight are never drawn.
+pub struct WeightedSampler {
+    weight: Vec<f64>,
+    // 1-based Fenwick trees: node i covers the items (i - lowbit(i), i].
+    tree_sum: Vec<f64>,
+    tree_count: Vec<usize>,
+    // Segment tree of (largest weight, smallest positive weight): node i covers nodes 2i and 2i + 1,
+    // and the leaves, from node `num_leaf` on, the items (padded with zero weights).
+    tree_extreme: Vec<(f64, f64)>,
+    num_leaf: usize,
+}
+
+impl WeightedSampler {
+    /// Builds the trees in O(k).
+    pub fn new(weight: Vec<f64>) -> Self {
+        let k = weight.len();
+        let mut tree_sum = vec![0.0; k + 1];
+        let mut tree_count = vec![0; k + 1];
+        for (item, &w) in weight.iter().enumerate() {
+            tree_sum[item + 1] = w;
+            tree_count[item + 1] = (w > 0.0) as usize;
+        }
+        for node in 1..=k {
+            let parent = node + lowbit(node);
+            if parent <= k {
+                tree_sum[parent] += tree_sum[node];
+                tree_count[parent] += tree_count[node];
+            }
+        }
+        let num_leaf = k.next_power_of_two();
+        let mut tree_extreme = vec![extreme(0.0); 2 * num_leaf];
+        for (item, &w) in weight.iter().enumerate() {
+            tree_extreme[num_leaf + item] = extreme(w);
+        }
+        for node in (1..num_leaf).rev() {
+            tree_extreme[node] = combine(tree_extreme[2 * node], tree_extreme[2 * node + 1]);
+        }
+        WeightedSampler { weight, tree_sum, tree_count, tree_extreme, num_leaf }
+    }
+
+    pub fn len(&self) -> usize {
+        self.weight.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weight.is_empty()
+    }
+
+    pub fn weight(&self, item: usize) -> f64 {
+        self.weight[item]
+    }
+
+    pub fn set(&mut self, item: usize, weight: f64) {
+        let delta = weight - self.weight[item];
+        let was_positive = self.weight[item] > 0.0;
+        let is_positive = weight > 0.0;
+        self.weight[item] = weight;
+        let mut node = item + 1;
+        while node <= self.len() {
+            self.tree_sum[node] += delta;
+            if is_positive && !was_positive {
+                self.tree_count[node] += 1;
+            } else if was_positive && !is_positive {
+                self.tree_count[node] -= 1;
+            }
+            node += lowbit(node);
+        }
+        let mut node = self.num_leaf + item;
+        self.tree_extreme[node] = extreme(weight);
+        while node > 1 {
+            node /= 2;
+            self.tree_extreme[node] = combine(self.tree_extreme[2 * node], self.tree_extreme[2 * node + 1]);
+        }
+    }
+
+    /// Number of items of positive weight.
+    pub fn num_positive(&self) -> usize {
+        let mut node = self.len();
+        let mut count = 0;
+        while node > 0 {
+            count += self.tree_count[node];
+            node -= lowbit(node);
+        }
+        count
+    }
+
+    /// Draws an item with probability weight / total weight; `None` when every weight is zero.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<usize> {
+        if self.num_positive() == 0 {
+            return None;
+        }
+        let item = self.descend(rng.random::<f64>(), |sum, _| sum);
+        Some(self.nearest(item, |w| w > 0.0))
+    }
+
+    /// Draws an item of positive weight with probability proportional to its shortfall from the largest weight,
+    /// (max - weight) / Σ (max - weight); `None` when all positive weights are equal.
+    pub fn sample_shortfall<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<usize> {
+        let (max, min_positive) = self.tree_extreme[1];
+        if min_positive >= max {
+            return None;
+        }
+        let item = self.descend(rng.random::<f64>(), |sum, count| max * count as f64 - sum);
+        Some(self.nearest(item, |w| w > 0.0 && w < max))
+    }
+
+    /// The item at which the running total of `value` over the items passes `marker` times its sum,
+    /// with `value` given the summed weight and the count of positive weights of a range of items.
+    fn descend(&self, marker: f64, value: impl Fn(f64, usize) -> f64) -> usize {
+        let k = self.len();
+        let mut remaining = 0.0;
+        let mut node = k;
+        while node > 0 {
+            remaining += value(self.tree_sum[node], self.tree_count[node]);
+            node -= lowbit(node);
+        }
+        remaining *= marker;
+
+        let mut position = 0;
+        let mut step = if k == 0 { 0 } else { 1 << k.ilog2() };
+        while step > 0 {
+            let next = position + step;
+            if next <= k {
+                let value = value(self.tree_sum[next], self.tree_count[next]);
+                if value <= remaining {
+                    remaining -= value;
+                    position = next;
+                }
+            }
+            step >>= 1;
+        }
+        position
+    }
+
+    /// The closest item to `item` whose weight passes `is_candidate`. The descent lands on such an item
+    /// except when rounding in the sums puts the marker on the boundary of a range without any.
+    fn nearest(&self, item: usize, is_candidate: impl Fn(f64) -> bool) -> usize {
+        let k = self.len();
+        let item = item.min(k - 1);
+        (0..k)
+            .flat_map(|offset| [item.checked_sub(offset), Some(item + offset).filter(|&after| after < k)])
+            .flatten()
+            .find(|&candidate| is_candidate(self.weight[candidate]))
+            .unwrap_or(item)
+    }
+}
+
+/// The leaf of the segment tree of an item of weight `w`: nothing smaller is positive when `w` is not.
+fn extreme(w: f64) -> (f64, f64) {
+    (w, if w > 0.0 { w } else { f64::INFINITY })
+}
+
+fn combine(first: (f64, f64), second: (f64, f64)) -> (f64, f64) {
+    (first.0.max(second.0), first.1.min(second.1))
+}
+
+fn lowbit(node: usize) -> usize {
+    node & node.wrapping_neg()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::ScenarioRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn the_extremes_follow_the_updates() {
+        let mut rng = ScenarioRng::seed_from_u64(4);
+        for k in [1, 2, 7, 8, 33] {
+            let mut sampler = WeightedSampler::new(vec![0.0; k]);
+            for _ in 0..200 {
+                // Mostly a few distinct weights, so that every positive weight is sometimes the same.
+                let weight = [0.0, 0.5, 2.0, rng.random::<f64>()][rng.random_range(0..4)];
+                sampler.set(rng.random_range(0..k), weight);
+                let max = sampler.weight.iter().cloned().fold(0.0, f64::max);
+                let min_positive = sampler.weight.iter().cloned().filter(|&w| w > 0.0).fold(f64::INFINITY, f64::min);
+                assert_eq!(sampler.tree_extreme[1], (max, min_positive));
+                let has_shortfall = sampler.weight.iter().any(|&w| w > 0.0 && w < max);
+                assert_eq!(sampler.sample_shortfall(&mut rng).is_some(), has_shortfall);
+            }
+        }
+    }
+}